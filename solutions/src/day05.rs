@@ -1,35 +1,120 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use aoc_2025::input;
 use std::cmp::max;
-use std::io::{self, Read};
+use std::fmt::Display;
+use std::str::FromStr;
 
 use std::env;
 
+// The integer types an ingredient id can be read as. Puzzle inputs fit in u64, but the same range
+// logic works for negative ids (i64) and for very large ones (u128).
+trait Id: Copy + Ord + Display + FromStr {
+  // The next id, or None at the top of the type (where `end + 1` would overflow)
+  fn successor(self) -> Option<Self>;
+  // How many ids lie between two ids, excluding one end. Always fits in a u128.
+  fn distance(self, other: Self) -> u128;
+}
+
+macro_rules! impl_id {
+  ($($t:ty),*) => {
+    $(impl Id for $t {
+      fn successor(self) -> Option<Self> {
+        self.checked_add(1)
+      }
+
+      fn distance(self, other: Self) -> u128 {
+        self.abs_diff(other) as u128
+      }
+    })*
+  };
+}
+
+impl_id!(u64, i64, u128, i128);
+
 peg::parser! {
   grammar ranges_parser() for str {
-    rule range() -> (u64, u64)
-      = start:number() "-" end:number() { (start, end) }
+    // "-5-10" is -5 to 10 and "5--3" is 5 to -3: a minus sign only counts as a sign right at the
+    // start of a number, the dash in between is always the range separator.
+    rule range<T: FromStr>() -> (T, T)
+      = start:number::<T>() "-" end:number::<T>() { (start, end) }
 
-    rule number() -> u64
-      = n:$(['0'..='9']+) {? n.parse().or(Err("Cant parse u64")) }
+    rule number<T: FromStr>() -> T
+      = n:$("-"? ['0'..='9']+) {? n.parse().or(Err("Cant parse number")) }
 
-    rule range_list() -> Vec<(u64, u64)>
-      = l:(range() ** "\n") { l }
+    rule range_list<T: FromStr>() -> Vec<(T, T)>
+      = l:range::<T>() ** "\n" { l }
 
-    rule number_list() -> Vec<u64>
-      = l:(number() ** "\n") { l }
+    rule number_list<T: FromStr>() -> Vec<T>
+      = l:number::<T>() ** "\n" { l }
 
-    pub rule data() -> (Vec<(u64, u64)>, Vec<u64>)
-      = rl:range_list() "\n"+ nl:number_list() "\n"* { (rl, nl) }
+    pub rule data<T: FromStr>() -> (Vec<(T, T)>, Vec<T>)
+      = rl:range_list::<T>() "\n"+ nl:number_list::<T>() "\n"* { (rl, nl) }
   }
 }
 
-fn main() -> Result<()> {
-  let mut data = String::new();
-  io::stdin().read_to_string(&mut data)?;
-  let (ranges, items) = ranges_parser::data(&data)?;
+// Sorts the ranges and joins every pair that overlaps or touches (e.g. 3-5 and 6-8 become 3-8).
+// Reversed ranges like 8-3 contain nothing, same as in pt1, so they are dropped.
+fn merge_ranges<T: Id>(ranges: &[(T, T)]) -> Vec<(T, T)> {
+  let mut sorted = ranges.iter().filter(|(start, end)| start <= end).copied().collect::<Vec<_>>();
+  sorted.sort();
 
-  let args: Vec<String> = env::args().collect();
-  let solution_part = args.get(1).map(|x| x.as_str()).unwrap_or("pt1");
+  let mut merged: Vec<(T, T)> = vec![];
+  for (start, end) in sorted {
+    match merged.last_mut() {
+      // A range ending at the largest id swallows everything after it
+      Some((_, last_end)) if last_end.successor().is_none_or(|next| start <= next) => {
+        *last_end = max(*last_end, end)
+      }
+      _ => merged.push((start, end)),
+    }
+  }
+  merged
+}
+
+// 0-18446744073709551615 holds 2^64 ids, one more than fits in a u64. Only the full u128 or i128
+// range is too big to count.
+fn union_len<T: Id>(ranges: &[(T, T)]) -> Result<u128> {
+  merge_ranges(ranges)
+    .iter()
+    .try_fold(0u128, |sum, (start, end)| {
+      sum.checked_add(start.distance(*end))?.checked_add(1)
+    })
+    .ok_or(anyhow!("Union of ranges has more than u128::MAX ids"))
+}
+
+fn show<T: Id>(range: Option<&(T, T)>) -> String {
+  range.map(|(start, end)| format!("{}-{}", start, end)).unwrap_or("none".to_string())
+}
+
+// Explains a single id: the ranges containing it and the merged interval they form, or for a
+// spoiled id the closest ranges that end below it and start above it.
+fn query<T: Id>(id: T, ranges: &[(T, T)], merged: &[(T, T)]) -> String {
+  let covering =
+    ranges.iter().filter(|(start, end)| id >= *start && id <= *end).collect::<Vec<_>>();
+
+  if covering.is_empty() {
+    let below = ranges.iter().filter(|(start, end)| start <= end && *end < id).max_by_key(|r| r.1);
+    let above =
+      ranges.iter().filter(|(start, end)| start <= end && *start > id).min_by_key(|r| r.0);
+    return format!(
+      "{}: spoiled, nearest below {}, nearest above {}",
+      id,
+      show(below),
+      show(above)
+    );
+  }
+
+  let ix = merged.partition_point(|(start, _)| *start <= id);
+  format!(
+    "{}: fresh, covered by {}; merged {}",
+    id,
+    covering.into_iter().map(|r| show(Some(r))).collect::<Vec<_>>().join(", "),
+    show(merged.get(ix - 1))
+  )
+}
+
+fn solve<T: Id>(data: &str, solution_part: &str, query_ids: &[String]) -> Result<()> {
+  let (ranges, items) = ranges_parser::data::<T>(data)?;
 
   if solution_part == "pt1" {
     let count = items
@@ -38,23 +123,45 @@ fn main() -> Result<()> {
       .count();
 
     println!("{}", count);
-  } else {
-    let mut ranges = ranges.clone();
-    ranges.sort();
-
-    let mut count = 0u64;
-    let mut accounted = 0u64;
-    for (start, end) in ranges {
-      if end < accounted {
-        continue;
-      }
-      count += end - max(start, accounted) + 1;
-      accounted = end + 1;
-    }
+  } else if solution_part == "query" {
+    // Ids given after "query" take precedence over the ones in the input
+    let ids = if query_ids.is_empty() {
+      items
+    } else {
+      query_ids
+        .iter()
+        .map(|a| a.parse::<T>().map_err(|_| anyhow!("Invalid id {}", a)))
+        .collect::<Result<Vec<_>>>()?
+    };
 
-    println!("{}", count);
+    let merged = merge_ranges(&ranges);
+    for id in ids {
+      println!("{}", query(id, &ranges, &merged));
+    }
+  } else {
+    println!("{}", union_len(&ranges)?);
   }
 
   Ok(())
 }
+
+fn main() -> Result<()> {
+  // Ranges and ids can be annotated with "# why this range exists"
+  let data = input::strip_comments(&input::read_stdin()?, "#");
+
+  // --ids=i64 (or u128, i128) switches the id type, the default is u64
+  let (id_flags, args): (Vec<String>, Vec<String>) =
+    env::args().partition(|a| a.starts_with("--ids="));
+  let id_type = id_flags.last().map(|f| &f["--ids=".len()..]).unwrap_or("u64");
+  let solution_part = args.get(1).map(|x| x.as_str()).unwrap_or("pt1");
+  let query_ids = args.get(2..).unwrap_or(&[]);
+
+  match id_type {
+    "u64" => solve::<u64>(&data, solution_part, query_ids),
+    "i64" => solve::<i64>(&data, solution_part, query_ids),
+    "u128" => solve::<u128>(&data, solution_part, query_ids),
+    "i128" => solve::<i128>(&data, solution_part, query_ids),
+    other => Err(anyhow!("Unknown id type {}", other)),
+  }
+}
 // 334877939080182 too low
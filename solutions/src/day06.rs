@@ -1,91 +1,292 @@
-use anyhow::Result;
-use std::io::{self, Read};
+use anyhow::{Context, Result, anyhow};
+use aoc_2025::input;
+use num_bigint::BigInt;
+use std::cmp::{max, min};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
 
 use std::env;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum Operator {
   Add,
   Mul,
+  Sub,
+  Div,
+  Max,
+  Min,
 }
 
-impl From<&str> for Operator {
-  fn from(value: &str) -> Self {
+impl TryFrom<&str> for Operator {
+  type Error = anyhow::Error;
+
+  fn try_from(value: &str) -> Result<Self> {
     match value {
-      "+" => Self::Add,
-      _ => Self::Mul, // cheating
+      "+" => Ok(Self::Add),
+      "*" => Ok(Self::Mul),
+      "-" => Ok(Self::Sub),
+      "/" => Ok(Self::Div),
+      "max" => Ok(Self::Max),
+      "min" => Ok(Self::Min),
+      _ => Err(anyhow!("Unknown operator {}", value)),
     }
   }
 }
 
-peg::parser! {
-  grammar problem() for str {
-
-    rule _() = quiet!{[' ' | '\t']*}
+impl fmt::Display for Operator {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let symbol = match self {
+      Self::Add => "+",
+      Self::Mul => "*",
+      Self::Sub => "-",
+      Self::Div => "/",
+      Self::Max => "max",
+      Self::Min => "min",
+    };
+    write!(f, "{}", symbol)
+  }
+}
 
-    rule number() -> u64
-      = n:$(['0'..='9']+) {? n.parse().or(Err("Cant parse u64")) }
+// Numbers a worksheet can be evaluated in. u64 reports overflow as None, BigInt never overflows
+// (and lets - go below zero). Division by zero is None for both. Cells are parsed straight into
+// the type, so --big also takes numbers past u64 in the worksheet itself.
+trait Value: Clone + Ord + fmt::Display + From<u64> + FromStr {
+  const NAME: &'static str;
 
-    rule oper() -> Operator
-      = op:$("*" / "+") { op.into() }
+  fn combine(&self, op: Operator, other: &Self) -> Option<Self>;
+}
 
+impl Value for u64 {
+  const NAME: &'static str = "u64";
 
-    rule number_row() -> Vec<u64>
-      = _ l:(number() ++ (" " _)) _ { l }
+  fn combine(&self, op: Operator, other: &Self) -> Option<Self> {
+    match op {
+      Operator::Add => self.checked_add(*other),
+      Operator::Mul => self.checked_mul(*other),
+      Operator::Sub => self.checked_sub(*other),
+      Operator::Div => self.checked_div(*other),
+      Operator::Max => Some(max(*self, *other)),
+      Operator::Min => Some(min(*self, *other)),
+    }
+  }
+}
 
-    pub rule pt1() -> (Vec<Vec<u64>>, Vec<Operator>)
-      = l:(number_row() ** ("\n")) "\n" _ o:(oper() ** (" "+)) _ "\n"* { (l, o) }
+impl Value for BigInt {
+  const NAME: &'static str = "big integer";
 
+  fn combine(&self, op: Operator, other: &Self) -> Option<Self> {
+    match op {
+      Operator::Add => Some(self + other),
+      Operator::Mul => Some(self * other),
+      Operator::Sub => Some(self - other),
+      Operator::Div if *other == BigInt::ZERO => None,
+      Operator::Div => Some(self / other),
+      Operator::Max => Some(max(self, other).clone()),
+      Operator::Min => Some(min(self, other).clone()),
+    }
+  }
+}
 
-    rule pt2_problem() -> (Vec<u64>, Operator)
-      = _ l:(number() ++ (" " _)) _ o:oper() { (l, o) }
+impl Operator {
+  // Folds the numbers left to right in the order they were read, so 10 2 3 with - is 10 - 2 - 3
+  fn apply<T: Value>(&self, items: &[T]) -> Result<T> {
+    let (first, rest) = items.split_first().ok_or(anyhow!("No numbers to {}", self))?;
+    rest.iter().try_fold(first.clone(), |acc, n| {
+      acc.combine(*self, n).ok_or_else(|| match self {
+        Self::Div => anyhow!("Division by zero in {} / {}", acc, n),
+        _ => anyhow!("{} {} {} does not fit in a {}", acc, self, n, T::NAME),
+      })
+    })
+  }
+}
 
-    pub rule pt2() -> Vec<(Vec<u64>, Operator)>
-      = l:(pt2_problem() ++ " ") _ { l }
+#[derive(Debug, PartialEq)]
+enum Alignment {
+  Left,
+  Right,
+  Centre,
+  Ragged,
+}
 
+impl fmt::Display for Alignment {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let name = match self {
+      Self::Left => "left-aligned",
+      Self::Right => "right-aligned",
+      Self::Centre => "centred",
+      Self::Ragged => "ragged",
+    };
+    write!(f, "{}", name)
   }
 }
 
-fn main() -> Result<()> {
-  let mut data = String::new();
-  io::stdin().read_to_string(&mut data)?;
+// One problem of the worksheet: the text of its number rows, cut out at the problem's columns and
+// padded to the same width, and the operator written below them.
+#[derive(Debug)]
+struct Problem {
+  columns: Range<usize>,
+  cells: Vec<String>,
+  operator: Operator,
+}
 
-  let args: Vec<String> = env::args().collect();
-  let solution_part = args.get(1).map(|x| x.as_str()).unwrap_or("pt1");
-
-  if solution_part == "pt1" {
-    let (rows, opers) = problem::pt1(&data)?;
-    let solution: u64 = (0..opers.len())
-      .map(|k| {
-        let items = rows.iter().map(|r| r[k]);
-        let oper = &opers[k];
-        match oper {
-          Operator::Add => items.sum::<u64>(),
-          Operator::Mul => items.product::<u64>(),
-        }
-      })
-      .sum();
-    println!("{}", solution);
-  } else {
-    let char_grid = data.split("\n").map(|l| l.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+fn parse_number<T: Value>(text: &str) -> Result<T> {
+  text.parse().map_err(|_| anyhow!("Invalid number {:?} for a {}", text, T::NAME))
+}
+
+impl Problem {
+  // How the numbers sit inside the block. Blocks where every number fills the whole width count
+  // as right-aligned. The column reading goes by position either way; this is what --show
+  // reports, to tell apart a worksheet that is meant to be ragged from a broken one.
+  fn alignment(&self) -> Alignment {
+    let padding = self
+      .cells
+      .iter()
+      .filter(|c| !c.trim().is_empty())
+      .map(|c| (c.len() - c.trim_start().len(), c.len() - c.trim_end().len()))
+      .collect::<Vec<_>>();
+
+    if padding.iter().all(|(_, right)| *right == 0) {
+      Alignment::Right
+    } else if padding.iter().all(|(left, _)| *left == 0) {
+      Alignment::Left
+    } else if padding.iter().all(|(left, right)| left.abs_diff(*right) <= 1) {
+      Alignment::Centre
+    } else {
+      Alignment::Ragged
+    }
+  }
+
+  // The human reading: every row is one number
+  fn rows<T: Value>(&self) -> Result<Vec<T>> {
+    self.cells.iter().map(|c| c.trim()).filter(|c| !c.is_empty()).map(parse_number).collect()
+  }
 
-    let lines = char_grid.len();
-    let longest_len = char_grid.iter().max_by_key(|v| v.len()).unwrap().len();
-    let fixed_data: String = (0..longest_len)
+  // The cephalopod reading: every column, top to bottom, is one number, rightmost column first
+  fn columns<T: Value>(&self) -> Result<Vec<T>> {
+    (0..self.columns.len())
       .rev()
-      .map(|col| (0..lines).map(|l| char_grid[l].get(col).unwrap_or(&' ')).collect::<String>())
-      .collect();
-
-    let problems = problem::pt2(&fixed_data)?;
-    let solution: u64 = problems
-      .into_iter()
-      .map(|(items, oper)| match oper {
-        Operator::Add => items.iter().sum::<u64>(),
-        Operator::Mul => items.iter().product::<u64>(),
+      .map(|col| {
+        self
+          .cells
+          .iter()
+          .filter_map(|c| c.chars().nth(col))
+          .filter(|c| *c != ' ')
+          .collect::<String>()
+      })
+      .filter(|digits| !digits.is_empty())
+      .map(|digits| parse_number(&digits))
+      .collect()
+  }
+}
+
+// The worksheet is split into problems at columns that are blank on every line, operator line
+// included. Both readings work on the same problem blocks.
+fn parse_worksheet(data: &str) -> Result<Vec<Problem>> {
+  let lines = data.lines().filter(|l| !l.trim().is_empty()).collect::<Vec<_>>();
+  let (oper_line, number_lines) = lines.split_last().ok_or(anyhow!("Empty worksheet"))?;
+
+  let char_grid = lines.iter().map(|l| l.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+  let width = char_grid.iter().map(|l| l.len()).max().unwrap_or(0);
+  let blank = (0..width)
+    .map(|col| char_grid.iter().all(|l| l.get(col).is_none_or(|c| c.is_whitespace())))
+    .collect::<Vec<_>>();
+
+  let mut blocks = vec![];
+  let mut col = 0;
+  while col < width {
+    if blank[col] {
+      col += 1;
+      continue;
+    }
+    let start = col;
+    while col < width && !blank[col] {
+      col += 1;
+    }
+    blocks.push(start..col);
+  }
+
+  // Editors strip trailing whitespace, so lines can end before the block does. Padding every cell
+  // back to the block width keeps left-aligned and ragged numbers in their columns.
+  let cut = |line: &str, cols: &Range<usize>| {
+    let cell = line.chars().skip(cols.start).take(cols.len()).collect::<String>();
+    format!("{:<width$}", cell, width = cols.len())
+  };
+
+  blocks
+    .iter()
+    .map(|cols| {
+      Ok(Problem {
+        columns: cols.clone(),
+        cells: number_lines.iter().map(|l| cut(l, cols)).collect(),
+        operator: Operator::try_from(cut(oper_line, cols).trim())?,
       })
-      .sum();
+    })
+    .collect()
+}
+
+fn label(ix: usize, p: &Problem) -> String {
+  format!(
+    "Problem {} (columns {}-{})",
+    ix + 1,
+    p.columns.start + 1,
+    p.columns.end
+  )
+}
+
+// Shows a problem block the way it was cut from the worksheet, followed by the numbers in the
+// order they were read, so a wrong reading can be spotted against the puzzle's worked example.
+fn render<T: Value>(ix: usize, p: &Problem, numbers: &[T], result: &T) -> String {
+  let block = p.cells.iter().map(|c| format!("  {}\n", c)).collect::<String>();
+  let expression = numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+  format!(
+    "{}, {}\n{}  {}\n  = {} = {}\n",
+    label(ix, p),
+    p.alignment(),
+    block,
+    p.operator,
+    expression.join(&format!(" {} ", p.operator)),
+    result
+  )
+}
+
+fn solve<T: Value>(problems: &[Problem], solution_part: &str, show: bool) -> Result<T> {
+  problems.iter().enumerate().try_fold(T::from(0), |total, (ix, p)| {
+    let numbers = if solution_part == "pt1" {
+      p.rows::<T>()?
+    } else {
+      p.columns::<T>()?
+    };
+    let result = p.operator.apply(&numbers).with_context(|| label(ix, p))?;
+
+    if show {
+      println!("{}", render(ix, p, &numbers, &result));
+    }
+
+    total.combine(Operator::Add, &result).ok_or(anyhow!(
+      "Grand total does not fit in a {}, try --big",
+      T::NAME
+    ))
+  })
+}
+
+const PARTS: [&str; 2] = ["pt1", "pt2"];
+
+fn main() -> Result<()> {
+  let data = input::read_stdin()?;
 
-    println!("{}", solution);
+  let args: Vec<String> = env::args().collect();
+  // Flags can come first, only a part name picks the part
+  let solution_part =
+    args.get(1).map(|x| x.as_str()).filter(|x| PARTS.contains(x)).unwrap_or("pt1");
+  let big = args.iter().any(|a| a == "--big");
+  let show = args.iter().any(|a| a == "--show");
+
+  let problems = parse_worksheet(&data)?;
+  if big {
+    println!("{}", solve::<BigInt>(&problems, solution_part, show)?);
+  } else {
+    println!("{}", solve::<u64>(&problems, solution_part, show)?);
   }
 
   Ok(())
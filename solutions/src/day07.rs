@@ -1,91 +1,476 @@
-use anyhow::Result;
-use itertools::Itertools;
-use std::io;
+use anyhow::{Result, anyhow};
+use aoc_2025::input;
+use num_bigint::BigUint;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
 
 use std::env;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 enum Point {
   Space,
   Splitter,
   Start,
+  // '/' and '\', turning the beam by 90 degrees
+  MirrorUp,
+  MirrorDown,
+  Absorber,
+  // Splitters that only send the beam out on their left ('<') or right ('>') side
+  SplitterLeft,
+  SplitterRight,
 }
 
-impl From<char> for Point {
-  fn from(value: char) -> Self {
+impl Point {
+  fn symbol(&self) -> char {
+    match self {
+      Self::Space => '.',
+      Self::Splitter => '^',
+      Self::Start => 'S',
+      Self::MirrorUp => '/',
+      Self::MirrorDown => '\\',
+      Self::Absorber => '#',
+      Self::SplitterLeft => '<',
+      Self::SplitterRight => '>',
+    }
+  }
+}
+
+impl TryFrom<char> for Point {
+  type Error = anyhow::Error;
+
+  fn try_from(value: char) -> Result<Self> {
     match value {
-      '^' => Self::Splitter,
-      'S' => Self::Start,
-      _ => Self::Space, // cheating
+      '.' => Ok(Self::Space),
+      '^' => Ok(Self::Splitter),
+      'S' => Ok(Self::Start),
+      '/' => Ok(Self::MirrorUp),
+      '\\' => Ok(Self::MirrorDown),
+      '#' => Ok(Self::Absorber),
+      '<' => Ok(Self::SplitterLeft),
+      '>' => Ok(Self::SplitterRight),
+      _ => Err(anyhow!("Unknown manifold component {:?}", value)),
     }
   }
 }
 
-fn main() -> Result<()> {
-  let args: Vec<String> = env::args().collect();
-  let solution_part = args.get(1).map(|x| x.as_str()).unwrap_or("pt1");
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum Direction {
+  Up,
+  Down,
+  Left,
+  Right,
+}
 
-  if solution_part == "pt1" {
-    let mut solution = 0;
-    let mut current_beams: Vec<usize> = vec![];
-    for line in io::stdin().lines() {
-      let pts: Vec<Point> = line?.chars().map(|c| c.into()).collect();
-      if current_beams.len() == 0 {
-        println!("Initializing");
-        current_beams =
-          pts.iter().enumerate().filter(|(_, pt)| **pt == Point::Start).map(|(ix, _)| ix).collect();
-
-        println!("Beaming {:?}", current_beams);
-      } else {
-        let (split_points, continued_beams): (Vec<usize>, Vec<usize>) =
-          current_beams.into_iter().partition(|ix| pts[*ix] == Point::Splitter);
-
-        solution += split_points.len();
-
-        current_beams = split_points
-          .iter()
-          .flat_map(|sp| vec![sp - 1, sp + 1])
-          .filter(|sp| *sp > 0 && *sp < pts.len())
-          .chain(continued_beams.into_iter())
-          .unique()
-          .collect();
+impl Direction {
+  fn delta(&self) -> (isize, isize) {
+    match self {
+      Self::Up => (-1, 0),
+      Self::Down => (1, 0),
+      Self::Left => (0, -1),
+      Self::Right => (0, 1),
+    }
+  }
+
+  fn vertical(&self) -> bool {
+    matches!(self, Self::Up | Self::Down)
+  }
+
+  // Direction after bouncing off a '/' mirror, '\' is the same with the rows flipped
+  fn bounce_up(&self) -> Direction {
+    match self {
+      Self::Up => Self::Right,
+      Self::Down => Self::Left,
+      Self::Left => Self::Down,
+      Self::Right => Self::Up,
+    }
+  }
+
+  fn bounce_down(&self) -> Direction {
+    match self {
+      Self::Up => Self::Left,
+      Self::Down => Self::Right,
+      Self::Left => Self::Up,
+      Self::Right => Self::Down,
+    }
+  }
+}
+
+// A beam entering a cell of the manifold, travelling in a direction
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+struct Beam {
+  row: usize,
+  col: usize,
+  dir: Direction,
+}
+
+// Where a beam goes after a cell: into another cell, out through the bottom of the manifold (a
+// timeline that made it through), or out any other way (a timeline that is gone).
+enum Target {
+  Cell(Beam),
+  Bottom,
+  Lost,
+}
+
+struct Manifold {
+  grid: Vec<Vec<Point>>,
+  width: usize,
+}
+
+// Timelines double at every splitter level, so deep manifolds overflow a u64. That is reported as
+// an error, BigUint is there for when the real number is wanted.
+trait Count: Clone + fmt::Display + From<u64> {
+  const NAME: &'static str;
+
+  fn plus(&self, other: &Self) -> Option<Self>;
+  fn log2(&self) -> f64;
+}
+
+impl Count for u64 {
+  const NAME: &'static str = "u64";
+
+  fn plus(&self, other: &Self) -> Option<Self> {
+    self.checked_add(*other)
+  }
+
+  fn log2(&self) -> f64 {
+    (*self as f64).log2()
+  }
+}
+
+impl Count for BigUint {
+  const NAME: &'static str = "big integer";
+
+  fn plus(&self, other: &Self) -> Option<Self> {
+    Some(self + other)
+  }
+
+  // Only the top 64 bits matter for the precision of an f64
+  fn log2(&self) -> f64 {
+    let shift = self.bits().saturating_sub(64);
+    let top = (self >> shift).iter_u64_digits().next().unwrap_or(0);
+    (top as f64).log2() + shift as f64
+  }
+}
+
+// Part 1 only cares whether a splitter was reached at all, part 2 about the timelines that leave
+// through the bottom.
+struct Simulation<T: Count> {
+  counts: HashMap<Beam, T>,
+  splits: usize,
+  timelines: T,
+}
+
+impl Manifold {
+  fn new(grid: Vec<Vec<Point>>) -> Manifold {
+    let width = grid.iter().map(|r| r.len()).max().unwrap_or(0);
+    Manifold { grid, width }
+  }
+
+  // Rows shorter than the widest one are treated as empty space past their end
+  fn at(&self, row: usize, col: usize) -> Point {
+    *self.grid[row].get(col).unwrap_or(&Point::Space)
+  }
+
+  fn starts(&self) -> Vec<Beam> {
+    (0..self.grid.len())
+      .flat_map(|row| (0..self.width).map(move |col| (row, col)))
+      .filter(|(row, col)| self.at(*row, *col) == Point::Start)
+      .map(|(row, col)| Beam {
+        row,
+        col,
+        dir: Direction::Down,
+      })
+      .collect()
+  }
+
+  fn target(&self, row: isize, col: isize, dir: Direction) -> Target {
+    // A beam going out the side is lost, even from the last row
+    if row < 0 || col < 0 || col >= self.width as isize {
+      Target::Lost
+    } else if row >= self.grid.len() as isize {
+      Target::Bottom
+    } else {
+      Target::Cell(Beam {
+        row: row as usize,
+        col: col as usize,
+        dir,
+      })
+    }
+  }
+
+  // Which sides of the beam a splitter sends it out on, or None if this cell doesn't split it.
+  // One-sided splitters only use one side and let horizontal beams through.
+  fn sides(&self, beam: &Beam) -> Option<Vec<isize>> {
+    match self.at(beam.row, beam.col) {
+      Point::Splitter => Some(vec![-1, 1]),
+      Point::SplitterLeft if beam.dir.vertical() => Some(vec![-1]),
+      Point::SplitterRight if beam.dir.vertical() => Some(vec![1]),
+      _ => None,
+    }
+  }
+
+  fn side_offset(beam: &Beam, side: isize) -> (isize, isize) {
+    if beam.dir.vertical() {
+      (0, side)
+    } else {
+      (side, 0)
+    }
+  }
+
+  // A splitter sends the beam on from the cells beside it, in the direction it was already going:
+  // a falling beam continues down from the left and right of the splitter, same as in the puzzle.
+  fn next(&self, beam: &Beam) -> Vec<Target> {
+    let (r, c) = (beam.row as isize, beam.col as isize);
+    let ahead = |dir: Direction| {
+      let (dr, dc) = dir.delta();
+      self.target(r + dr, c + dc, dir)
+    };
+
+    if let Some(sides) = self.sides(beam) {
+      let (dr, dc) = beam.dir.delta();
+      return sides
+        .into_iter()
+        .map(|side| {
+          let (sr, sc) = Manifold::side_offset(beam, side);
+          self.target(r + sr + dr, c + sc + dc, beam.dir)
+        })
+        .collect();
+    }
+
+    match self.at(beam.row, beam.col) {
+      Point::Absorber => vec![],
+      Point::MirrorUp => vec![ahead(beam.dir.bounce_up())],
+      Point::MirrorDown => vec![ahead(beam.dir.bounce_down())],
+      _ => vec![ahead(beam.dir)],
+    }
+  }
+
+  // The cells a beam shows up in: its own, and for a splitter the cells beside it that the new
+  // beams leave from, like the |^| in the puzzle statement
+  fn lit_cells(&self, beam: &Beam) -> Vec<(usize, usize)> {
+    let mut cells = vec![(beam.row, beam.col)];
+    for side in self.sides(beam).unwrap_or_default() {
+      let (sr, sc) = Manifold::side_offset(beam, side);
+      let (r, c) = (beam.row as isize + sr, beam.col as isize + sc);
+      if r >= 0 && c >= 0 && (r as usize) < self.grid.len() && (c as usize) < self.width {
+        cells.push((r as usize, c as usize));
       }
     }
-    println!("{}", solution);
-  } else {
-    let mut beam_counts = vec![];
-    for line in io::stdin().lines() {
-      let pts: Vec<Point> = line?.chars().map(|c| c.into()).collect();
-      if beam_counts.len() == 0 {
-        beam_counts = vec![0; pts.len()];
-        println!("Initializing");
-        for ix in pts.iter().enumerate().filter(|(_, pt)| **pt == Point::Start).map(|(ix, _)| ix) {
-          beam_counts[ix] = 1;
+    cells
+  }
+
+  // Timelines passing through every cell, as log2 since the counts get astronomically big. Cells
+  // no beam reaches are None.
+  fn heat<T: Count>(&self, counts: &HashMap<Beam, T>) -> Vec<Vec<Option<f64>>> {
+    let mut heat: Vec<Vec<Vec<f64>>> = vec![vec![vec![]; self.width]; self.grid.len()];
+    for (beam, count) in counts.iter() {
+      for (row, col) in self.lit_cells(beam) {
+        heat[row][col].push(count.log2());
+      }
+    }
+
+    // log2 of a sum of powers of two, without leaving log space
+    heat
+      .into_iter()
+      .map(|row| {
+        row
+          .into_iter()
+          .map(|logs| {
+            let top = logs.iter().copied().reduce(f64::max)?;
+            Some(top + logs.iter().map(|l| (l - top).exp2()).sum::<f64>().log2())
+          })
+          .collect()
+      })
+      .collect()
+  }
+
+  // The manifold with the beams drawn in like in the puzzle statement: | for a beam going up or
+  // down, - for one going sideways, + where both cross
+  fn draw<T: Count>(&self, counts: &HashMap<Beam, T>) -> String {
+    let mut marks = vec![vec![(false, false); self.width]; self.grid.len()];
+    for beam in counts.keys() {
+      for (row, col) in self.lit_cells(beam) {
+        let mark = &mut marks[row][col];
+        if beam.dir.vertical() {
+          mark.0 = true
+        } else {
+          mark.1 = true
+        }
+      }
+    }
+
+    (0..self.grid.len())
+      .map(|row| {
+        let line = (0..self.width)
+          .map(|col| match (self.at(row, col), marks[row][col]) {
+            (Point::Space, (true, true)) => '+',
+            (Point::Space, (true, false)) => '|',
+            (Point::Space, (false, true)) => '-',
+            (pt, _) => pt.symbol(),
+          })
+          .collect::<String>();
+        line + "\n"
+      })
+      .collect()
+  }
+
+  // Binary PPM, one 4x4 block per cell: black where no beam goes, dark grey components, and the
+  // rest coloured from dark red (few timelines) to white (the most)
+  fn write_heatmap<T: Count>(&self, counts: &HashMap<Beam, T>, path: &str) -> Result<()> {
+    const SCALE: usize = 4;
+    let heat = self.heat(counts);
+    let hottest = heat.iter().flatten().flatten().copied().reduce(f64::max).unwrap_or(0.0);
+
+    let colour = |row: usize, col: usize| -> [u8; 3] {
+      match heat[row][col] {
+        Some(h) => {
+          let t = if hottest > 0.0 { h / hottest } else { 1.0 };
+          let channel = |offset: f64| ((3.0 * t - offset).clamp(0.0, 1.0) * 255.0) as u8;
+          [channel(0.0).max(64), channel(1.0), channel(2.0)]
+        }
+        None if self.at(row, col) != Point::Space => [64, 64, 64],
+        None => [0, 0, 0],
+      }
+    };
+
+    let mut image = format!(
+      "P6\n{} {}\n255\n",
+      self.width * SCALE,
+      self.grid.len() * SCALE
+    )
+    .into_bytes();
+    for row in 0..self.grid.len() {
+      for _ in 0..SCALE {
+        for col in 0..self.width {
+          let pixel = colour(row, col);
+          for _ in 0..SCALE {
+            image.extend_from_slice(&pixel);
+          }
+        }
+      }
+    }
+
+    fs::write(path, image)?;
+    Ok(())
+  }
+
+  // Beams can now go sideways and back up, so rows can't simply be processed top to bottom.
+  // Instead the counts are pushed along the graph of beam states in topological order. A state
+  // that is never ready means the beam can run in a loop, with endless timelines.
+  fn simulate<T: Count>(&self) -> Result<Simulation<T>> {
+    let starts = self.starts();
+
+    let mut incoming: HashMap<Beam, usize> = starts.iter().map(|b| (*b, 0)).collect();
+    let mut queue: VecDeque<Beam> = starts.iter().copied().collect();
+    let mut seen: HashSet<Beam> = starts.iter().copied().collect();
+    while let Some(beam) = queue.pop_front() {
+      for t in self.next(&beam) {
+        if let Target::Cell(n) = t {
+          *incoming.entry(n).or_insert(0) += 1;
+          if seen.insert(n) {
+            queue.push_back(n);
+          }
         }
-      } else {
-        let adders = {
-          beam_counts
-            .iter()
-            .zip(pts.into_iter())
-            .enumerate()
-            .flat_map(|(ix, (bc, pt))| {
-              if pt == Point::Splitter {
-                vec![(ix - 1, *bc), (ix + 1, *bc), (ix, 0 - *bc)]
-              } else {
-                vec![]
-              }
-            })
-            .collect::<Vec<_>>()
-        };
-
-        for (ix, c) in adders.into_iter() {
-          beam_counts[ix] += c
+      }
+    }
+
+    let overflow = |beam: &Beam| {
+      anyhow!(
+        "Timeline count at row {} column {} does not fit in a {}, try --big",
+        beam.row + 1,
+        beam.col + 1,
+        T::NAME
+      )
+    };
+    let add = |counts: &mut HashMap<Beam, T>, beam: Beam, count: &T| -> Result<()> {
+      let current = counts.entry(beam).or_insert(T::from(0));
+      *current = current.plus(count).ok_or_else(|| overflow(&beam))?;
+      Ok(())
+    };
+
+    let mut counts: HashMap<Beam, T> = HashMap::new();
+    for s in starts.iter() {
+      add(&mut counts, *s, &T::from(1))?;
+    }
+    let mut ready: VecDeque<Beam> = starts.into_iter().filter(|b| incoming[b] == 0).collect();
+    let mut processed = 0;
+    let mut split_cells = HashSet::new();
+    let mut timelines = T::from(0);
+    while let Some(beam) = ready.pop_front() {
+      processed += 1;
+      let count = counts[&beam].clone();
+      if self.at(beam.row, beam.col) == Point::Splitter {
+        split_cells.insert((beam.row, beam.col));
+      }
+      for t in self.next(&beam) {
+        match t {
+          Target::Cell(n) => {
+            add(&mut counts, n, &count)?;
+            let remaining = incoming.get_mut(&n).unwrap();
+            *remaining -= 1;
+            if *remaining == 0 {
+              ready.push_back(n);
+            }
+          }
+          Target::Bottom => timelines = timelines.plus(&count).ok_or_else(|| overflow(&beam))?,
+          Target::Lost => {}
         }
       }
-      //println!("Beaming next {:?}", beam_counts);
     }
-    println!("{}", beam_counts.iter().sum::<usize>());
+
+    if processed < incoming.len() {
+      return Err(anyhow!(
+        "The beam can loop forever, the number of timelines is unbounded"
+      ));
+    }
+
+    Ok(Simulation {
+      counts,
+      splits: split_cells.len(),
+      timelines,
+    })
   }
+}
+
+fn report<T: Count>(manifold: &Manifold, solution_part: &str, args: &[String]) -> Result<()> {
+  let simulation = manifold.simulate::<T>()?;
 
+  if args.iter().any(|a| a == "--draw") {
+    print!("{}", manifold.draw(&simulation.counts));
+  }
+  if let Some(path) = args.iter().find_map(|a| a.strip_prefix("--heatmap=")) {
+    manifold.write_heatmap(&simulation.counts, path)?;
+  }
+
+  if solution_part == "pt1" {
+    println!("{}", simulation.splits);
+  } else {
+    println!("{}", simulation.timelines);
+  }
   Ok(())
 }
+
+const PARTS: [&str; 2] = ["pt1", "pt2"];
+
+fn main() -> Result<()> {
+  let args: Vec<String> = env::args().collect();
+  // Flags can come first, only a part name picks the part
+  let solution_part =
+    args.get(1).map(|x| x.as_str()).filter(|x| PARTS.contains(x)).unwrap_or("pt1");
+  let big = args.iter().any(|a| a == "--big");
+
+  // Normalized first, so trailing spaces from an editor don't count as unknown components
+  let grid = input::read_stdin()?
+    .lines()
+    .map(|l| l.chars().map(Point::try_from).collect::<Result<Vec<_>>>())
+    .collect::<Result<Vec<_>>>()?;
+  let manifold = Manifold::new(grid);
+
+  // Part 1 doesn't need the counts, but they still have to fit
+  if big || solution_part == "pt1" {
+    report::<BigUint>(&manifold, solution_part, &args)
+  } else {
+    report::<u64>(&manifold, solution_part, &args)
+  }
+}
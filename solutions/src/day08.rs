@@ -1,23 +1,127 @@
-use anyhow::Result;
-use std::cmp::{max, min};
-use std::collections::{HashMap, HashSet};
+use anyhow::{Result, anyhow};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::io::{self, Read};
 
 use std::env;
+use std::fmt;
+use std::fs;
 
-use std::hash::Hash;
+// A point with as many axes as the input has comma separated numbers per line
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+struct Coordinate(Vec<i64>);
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-struct Coordinate {
-  x: u64,
-  y: u64,
-  z: u64,
+impl Coordinate {
+  fn axis(&self, axis: usize) -> i64 {
+    self.0[axis]
+  }
+
+  fn dimensions(&self) -> usize {
+    self.0.len()
+  }
 }
 
-impl Coordinate {
-  pub fn distance(&self, c: &Coordinate) -> f64 {
-    (((self.x - c.x).pow(2) + (self.y - c.y).pow(2) + (self.z - c.z).pow(2)) as f64).sqrt()
+impl fmt::Display for Coordinate {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0.iter().join(","))
+  }
+}
+
+// How far apart two boxes are. Distances are exact integers so pairs compare without rounding,
+// `length` turns one back into the number shown to people. Every axis is scaled by its weight.
+trait Metric {
+  const NAME: &'static str;
+
+  // What one axis adds to the distance, it never exceeds the distance itself
+  fn contribution(&self, axis: usize, diff: u64) -> Option<u128>;
+  fn combine(a: u128, b: u128) -> Option<u128>;
+  fn length(distance: u128) -> f64;
+
+  fn distance(&self, a: &[i64], b: &[i64]) -> Option<u128> {
+    a.iter().zip(b).enumerate().try_fold(0, |acc, (axis, (a, b))| {
+      Self::combine(acc, self.contribution(axis, a.abs_diff(*b))?)
+    })
+  }
+}
+
+// Squared, the square root only comes in for `length`
+struct Euclidean {
+  weights: Vec<u64>,
+}
+
+impl Metric for Euclidean {
+  const NAME: &'static str = "euclidean";
+
+  fn contribution(&self, axis: usize, diff: u64) -> Option<u128> {
+    (diff as u128).checked_mul(self.weights[axis] as u128)?.checked_pow(2)
   }
+
+  fn combine(a: u128, b: u128) -> Option<u128> {
+    a.checked_add(b)
+  }
+
+  fn length(distance: u128) -> f64 {
+    (distance as f64).sqrt()
+  }
+}
+
+struct Manhattan {
+  weights: Vec<u64>,
+}
+
+impl Metric for Manhattan {
+  const NAME: &'static str = "manhattan";
+
+  fn contribution(&self, axis: usize, diff: u64) -> Option<u128> {
+    (diff as u128).checked_mul(self.weights[axis] as u128)
+  }
+
+  fn combine(a: u128, b: u128) -> Option<u128> {
+    a.checked_add(b)
+  }
+
+  fn length(distance: u128) -> f64 {
+    distance as f64
+  }
+}
+
+struct Chebyshev {
+  weights: Vec<u64>,
+}
+
+impl Metric for Chebyshev {
+  const NAME: &'static str = "chebyshev";
+
+  fn contribution(&self, axis: usize, diff: u64) -> Option<u128> {
+    (diff as u128).checked_mul(self.weights[axis] as u128)
+  }
+
+  fn combine(a: u128, b: u128) -> Option<u128> {
+    Some(a.max(b))
+  }
+
+  fn length(distance: u128) -> f64 {
+    distance as f64
+  }
+}
+
+// Every pair is at most as far apart as the corners of the bounding box, so if those fit the
+// distances can be computed without checks afterwards.
+fn check_metric<M: Metric>(metric: &M, coordinates: &[Coordinate]) -> Result<()> {
+  let corner = |pick: fn(i64, i64) -> i64| {
+    coordinates
+      .iter()
+      .cloned()
+      .reduce(|a, b| Coordinate(a.0.iter().zip(&b.0).map(|(&a, &b)| pick(a, b)).collect()))
+  };
+  if let (Some(low), Some(high)) = (corner(i64::min), corner(i64::max)) {
+    metric.distance(&low.0, &high.0).ok_or(anyhow!(
+      "Distances don't fit in a u128 under the {} metric with these weights",
+      M::NAME
+    ))?;
+  }
+  Ok(())
 }
 
 impl From<&str> for Coordinate {
@@ -29,129 +133,663 @@ impl From<&str> for Coordinate {
 peg::parser! {
   grammar problem() for str {
     pub rule coordinate() -> Coordinate
-      = x:number() "," y:number() "," z:number() { Coordinate {x, y, z} }
+      = c:number() ++ "," { Coordinate(c) }
 
-    rule number() -> u64
-      = n:$(['0'..='9']+) {? n.parse().or(Err("Cant parse f64")) }
+    rule number() -> i64
+      = n:$(['0'..='9']+) {? n.parse().or(Err("Cant parse i64")) }
 
     pub rule coordinate_list() -> Vec<Coordinate>
       = l:(coordinate() ** "\n") "\n"* { l }
   }
 }
 
-fn all_linked<T>(item: &T, links: &Vec<(&T, &T)>, linked: &mut HashSet<T>)
-where
-  T: Eq + Hash + Copy,
-{
-  for x in links.iter().filter(|(a, b)| a == &item || b == &item).flat_map(|(a, b)| vec![a, b]) {
-    if linked.get(x) == None {
-      linked.insert(**x);
-      all_linked(*x, links, linked);
+// Disjoint-set forest over the indices of the junction boxes, with path compression and union
+// by size. Both parts join circuits through it.
+struct DisjointSet {
+  parent: Vec<usize>,
+  size: Vec<usize>,
+  components: usize,
+}
+
+impl DisjointSet {
+  fn new(n: usize) -> DisjointSet {
+    DisjointSet {
+      parent: (0..n).collect(),
+      size: vec![1; n],
+      components: n,
+    }
+  }
+
+  fn find(&mut self, item: usize) -> usize {
+    let mut root = item;
+    while self.parent[root] != root {
+      root = self.parent[root];
+    }
+    let mut current = item;
+    while self.parent[current] != root {
+      let next = self.parent[current];
+      self.parent[current] = root;
+      current = next;
     }
+    root
+  }
+
+  // Joins the sets of both items, returns false if they already were in the same set
+  fn union(&mut self, a: usize, b: usize) -> bool {
+    let (ra, rb) = (self.find(a), self.find(b));
+    if ra == rb {
+      return false;
+    }
+    let (big, small) = if self.size[ra] >= self.size[rb] {
+      (ra, rb)
+    } else {
+      (rb, ra)
+    };
+    self.parent[small] = big;
+    self.size[big] += self.size[small];
+    self.components -= 1;
+    true
+  }
+
+  fn size_of(&mut self, item: usize) -> usize {
+    let root = self.find(item);
+    self.size[root]
+  }
+
+  fn component_sizes(&mut self) -> Vec<usize> {
+    let roots = (0..self.parent.len()).filter(|ix| self.find(*ix) == *ix).collect::<Vec<_>>();
+    roots.into_iter().map(|ix| self.size[ix]).collect()
   }
 }
 
-fn main() -> Result<()> {
-  let mut data = String::new();
-  io::stdin().read_to_string(&mut data)?;
-  let coordinates = problem::coordinate_list(&data)?;
+// Implicit k-d tree: the box indices are ordered so that the median of every slice splits it on
+// the axis for its depth, left half below and right half above. The axes of every box are copied
+// into one flat list in that same order, so a search walks through memory that sits together.
+struct KdTree<'a, M: Metric> {
+  coordinates: &'a [Coordinate],
+  dimensions: usize,
+  metric: &'a M,
+  order: Vec<usize>,
+  points: Vec<i64>,
+}
 
-  let args: Vec<String> = env::args().collect();
-  let solution_part = args.get(1).map(|x| x.as_str()).unwrap_or("pt1");
-  let max_connections = args.get(2).map(|x| str::parse::<usize>(x).unwrap_or(10)).unwrap_or(10);
-  let mut potential_links = coordinates
-    .iter()
-    .enumerate()
-    .flat_map(|(ix, c1)| {
-      coordinates
-        .iter()
-        .enumerate()
-        .flat_map(|(jx, c2)| if jx > ix { vec![(c1, c2)] } else { vec![] })
-        .collect::<Vec<_>>()
-    })
-    .collect::<Vec<_>>();
+impl<'a, M: Metric> KdTree<'a, M> {
+  fn new(coordinates: &'a [Coordinate], metric: &'a M) -> KdTree<'a, M> {
+    let dimensions = coordinates.first().map_or(1, Coordinate::dimensions);
+    let mut order = (0..coordinates.len()).collect::<Vec<_>>();
+    Self::build(coordinates, dimensions, &mut order, 0);
+    let points = order.iter().flat_map(|&ix| coordinates[ix].0.iter().copied()).collect();
+    KdTree {
+      coordinates,
+      dimensions,
+      metric,
+      order,
+      points,
+    }
+  }
 
-  potential_links.sort_by(|(p1, p2), (p3, p4)| f64::total_cmp(&p1.distance(p2), &p3.distance(p4)));
+  // The axes of the box at a position in the tree
+  fn point(&self, position: usize) -> &[i64] {
+    &self.points[position * self.dimensions..(position + 1) * self.dimensions]
+  }
 
-  if solution_part == "pt1" {
-    let links: Vec<_> = potential_links.into_iter().take(max_connections).collect();
-
-    let mut groups: Vec<HashSet<Coordinate>> = vec![];
-    let mut belonging: HashMap<Coordinate, usize> = HashMap::new();
-
-    for c in coordinates.iter() {
-      let group_id = match belonging.get(c) {
-        Some(id) => *id,
-        None => {
-          groups.push(HashSet::from_iter(vec![*c].into_iter()));
-          let id = groups.len() - 1;
-          id
+  fn distance(&self, a: &[i64], b: &[i64]) -> u128 {
+    self.metric.distance(a, b).expect("Distances are checked against the bounding box")
+  }
+
+  fn build(coordinates: &[Coordinate], dimensions: usize, order: &mut [usize], depth: usize) {
+    if order.len() <= 1 {
+      return;
+    }
+    let mid = order.len() / 2;
+    let axis = depth % dimensions;
+    order.select_nth_unstable_by_key(mid, |&ix| coordinates[ix].axis(axis));
+    let (left, right) = order.split_at_mut(mid);
+    Self::build(coordinates, dimensions, left, depth + 1);
+    Self::build(coordinates, dimensions, &mut right[1..], depth + 1);
+  }
+
+  // The k nearest other boxes to `item`, ordered by distance and then by index
+  fn nearest(&self, item: usize, k: usize) -> Vec<(u128, usize)> {
+    let mut best = BinaryHeap::new();
+    self.search(item, k, 0, self.order.len(), 0, &mut best);
+    best.into_sorted_vec()
+  }
+
+  fn search(
+    &self,
+    item: usize,
+    k: usize,
+    lo: usize,
+    hi: usize,
+    depth: usize,
+    best: &mut BinaryHeap<(u128, usize)>,
+  ) {
+    if lo >= hi {
+      return;
+    }
+    let mid = lo + (hi - lo) / 2;
+    let candidate = self.order[mid];
+    let (target, point) = (&self.coordinates[item].0, self.point(mid));
+    if candidate != item {
+      let key = (self.distance(target, point), candidate);
+      if best.len() < k {
+        best.push(key);
+      } else if best.peek().is_some_and(|worst| key < *worst) {
+        best.pop();
+        best.push(key);
+      }
+    }
+
+    let axis = depth % self.dimensions;
+    let (a, b) = (target[axis], point[axis]);
+    let (near, far) = if a < b {
+      ((lo, mid), (mid + 1, hi))
+    } else {
+      ((mid + 1, hi), (lo, mid))
+    };
+    self.search(item, k, near.0, near.1, depth + 1, best);
+    // A box on the other side of the plane can still tie with the worst one and win on index
+    let plane = self.metric.contribution(axis, a.abs_diff(b)).unwrap_or(u128::MAX);
+    if best.len() < k || best.peek().is_some_and(|&(worst, _)| plane <= worst) {
+      self.search(item, k, far.0, far.1, depth + 1, best);
+    }
+  }
+}
+
+// Yields every pair of boxes lazily, ordered by (distance, ix, jx). Ties are broken by the order
+// of the boxes in the input, so every run links the same pairs. Each box keeps a batch of its
+// nearest neighbours, refetched with twice the size once it runs out, and a heap merges the heads
+// of all those sorted streams. Every pair comes up once from each of its boxes, it's only yielded
+// from the lower index. Looking on both sides keeps the search just as quick for the last boxes
+// as for the first.
+struct NearestPairs<'a, M: Metric> {
+  tree: KdTree<'a, M>,
+  neighbours: Vec<Vec<(u128, usize)>>,
+  cursor: Vec<usize>,
+  batch: Vec<usize>,
+  // (distance, lower index, higher index, the box whose stream it came from)
+  heap: BinaryHeap<Reverse<(u128, usize, usize, usize)>>,
+}
+
+fn pair(distance: u128, ix: usize, jx: usize) -> Reverse<(u128, usize, usize, usize)> {
+  Reverse((distance, ix.min(jx), ix.max(jx), ix))
+}
+
+const FIRST_BATCH: usize = 8;
+
+impl<'a, M: Metric> NearestPairs<'a, M> {
+  fn new(coordinates: &'a [Coordinate], metric: &'a M) -> NearestPairs<'a, M> {
+    let tree = KdTree::new(coordinates, metric);
+    // Boxes next to each other in the tree look through the same part of it, going in that order
+    // keeps it in the cache
+    let mut neighbours = vec![vec![]; coordinates.len()];
+    for &ix in &tree.order {
+      neighbours[ix] = tree.nearest(ix, FIRST_BATCH);
+    }
+    let heap = neighbours
+      .iter()
+      .enumerate()
+      .filter_map(|(ix, n)| n.first().map(|&(d, jx)| pair(d, ix, jx)))
+      .collect();
+    NearestPairs {
+      tree,
+      cursor: vec![0; coordinates.len()],
+      batch: vec![FIRST_BATCH; coordinates.len()],
+      neighbours,
+      heap,
+    }
+  }
+}
+
+impl<M: Metric> Iterator for NearestPairs<'_, M> {
+  type Item = (u128, usize, usize);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let Reverse((distance, ix, jx, from)) = self.heap.pop()?;
+      self.cursor[from] += 1;
+      // A full batch that ran out may have more neighbours behind it, a partial one is all there
+      // is
+      let (cursor, batch) = (self.cursor[from], self.batch[from]);
+      if cursor == self.neighbours[from].len() && cursor == batch {
+        self.batch[from] *= 2;
+        self.neighbours[from] = self.tree.nearest(from, self.batch[from]);
+      }
+      if let Some(&(d, next)) = self.neighbours[from].get(self.cursor[from]) {
+        self.heap.push(pair(d, from, next));
+      }
+      if from == ix {
+        return Some((distance, ix, jx));
+      }
+    }
+  }
+}
+
+// A link of the spanning tree, `rank` is its position among all pairs ordered by distance and
+// `size` is the size of the circuit it formed.
+#[derive(Debug, Clone, Copy)]
+struct Link {
+  a: usize,
+  b: usize,
+  length: f64,
+  rank: usize,
+  size: usize,
+}
+
+// Kruskal's minimum spanning tree, the links are kept in the order the circuits merged.
+struct SpanningTree {
+  links: Vec<Link>,
+}
+
+impl SpanningTree {
+  fn new<M: Metric>(coordinates: &[Coordinate], metric: &M) -> SpanningTree {
+    let mut circuits = DisjointSet::new(coordinates.len());
+    let mut links = Vec::new();
+    for (rank, (distance, a, b)) in NearestPairs::new(coordinates, metric).enumerate() {
+      if circuits.components == 1 {
+        break;
+      }
+      if circuits.union(a, b) {
+        links.push(Link {
+          a,
+          b,
+          length: M::length(distance),
+          rank,
+          size: circuits.size_of(a),
+        });
+      }
+    }
+    SpanningTree { links }
+  }
+
+  fn total_length(&self) -> f64 {
+    self.links.iter().fold(0.0, |total, link| total + link.length)
+  }
+
+  // The link that joined the last two circuits into one
+  fn final_link(&self) -> Option<&Link> {
+    self.links.last()
+  }
+
+  fn to_dot(&self, coordinates: &[Coordinate]) -> String {
+    let mut dot = String::from("graph circuits {\n");
+    dot += &format!("  label=\"total length {:.3}\";\n", self.total_length());
+    for (ix, c) in coordinates.iter().enumerate() {
+      dot += &format!("  n{} [label=\"{}\"];\n", ix, c);
+    }
+    let last = self.links.len().saturating_sub(1);
+    for (ix, link) in self.links.iter().enumerate() {
+      let style = if ix == last { ", color=red" } else { "" };
+      dot += &format!(
+        "  n{} -- n{} [label=\"{:.3}\"{}];\n",
+        link.a, link.b, link.length, style
+      );
+    }
+    dot + "}\n"
+  }
+
+  fn to_csv(&self, coordinates: &[Coordinate]) -> String {
+    let dimensions = coordinates.first().map_or(0, Coordinate::dimensions);
+    let axes = |side| (0..dimensions).map(move |axis| format!("{}{}", side, axis));
+    let mut csv = format!(
+      "merge,a,b,{},{},distance,size\n",
+      axes("a").join(","),
+      axes("b").join(",")
+    );
+    for (ix, link) in self.links.iter().enumerate() {
+      let (a, b) = (&coordinates[link.a], &coordinates[link.b]);
+      csv += &format!(
+        "{},{},{},{},{},{:.3},{}\n",
+        ix, link.a, link.b, a, b, link.length, link.size
+      );
+    }
+    csv
+  }
+
+  fn write(&self, coordinates: &[Coordinate], path: &str) -> Result<()> {
+    let contents = match path.rsplit_once('.').map(|(_, ext)| ext) {
+      Some("dot" | "gv") => self.to_dot(coordinates),
+      Some("csv") => self.to_csv(coordinates),
+      _ => {
+        return Err(anyhow!(
+          "Can't tell the format of {}, use a .dot or .csv file",
+          path
+        ));
+      }
+    };
+    fs::write(path, contents)?;
+    Ok(())
+  }
+}
+
+// Two clusters joining at `height`, single linkage makes that the length of the spanning tree
+// link between them. Nodes below the number of boxes are the boxes themselves, merge k is node
+// boxes + k. `a` and `b` are the boxes the link runs between.
+struct Merge {
+  left: usize,
+  right: usize,
+  a: usize,
+  b: usize,
+  height: f64,
+  size: usize,
+}
+
+// Single-linkage clustering of the boxes, the merges come in increasing height.
+struct Dendrogram {
+  leaves: usize,
+  merges: Vec<Merge>,
+}
+
+enum Token {
+  Node(usize, Option<f64>),
+  Text(String),
+}
+
+impl Dendrogram {
+  fn new(tree: &SpanningTree, leaves: usize) -> Dendrogram {
+    let mut clusters = DisjointSet::new(leaves);
+    let mut node = (0..leaves).collect::<Vec<_>>();
+    let mut merges = Vec::new();
+    for link in &tree.links {
+      let (left, right) = (node[clusters.find(link.a)], node[clusters.find(link.b)]);
+      clusters.union(link.a, link.b);
+      node[clusters.find(link.a)] = leaves + merges.len();
+      merges.push(Merge {
+        left,
+        right,
+        a: link.a,
+        b: link.b,
+        height: link.length,
+        size: link.size,
+      });
+    }
+    Dendrogram { leaves, merges }
+  }
+
+  fn root(&self) -> Option<usize> {
+    match self.leaves {
+      0 => None,
+      _ => Some(self.leaves + self.merges.len() - 1),
+    }
+  }
+
+  fn height(&self, node: usize) -> f64 {
+    node.checked_sub(self.leaves).map_or(0.0, |k| self.merges[k].height)
+  }
+
+  // Branch lengths are the difference in height with the parent. Built with an explicit stack,
+  // a chain of boxes would nest as deep as there are boxes.
+  fn to_newick(&self) -> String {
+    let mut newick = String::new();
+    let mut stack = self.root().map(|root| Token::Node(root, None)).into_iter().collect::<Vec<_>>();
+    while let Some(token) = stack.pop() {
+      match token {
+        Token::Text(text) => newick += &text,
+        Token::Node(node, parent) => {
+          let branch = parent.map(|h| format!(":{:.3}", h - self.height(node))).unwrap_or_default();
+          if node < self.leaves {
+            newick += &format!("n{}{}", node, branch);
+          } else {
+            let merge = &self.merges[node - self.leaves];
+            newick += "(";
+            stack.push(Token::Text(format!("){}", branch)));
+            stack.push(Token::Node(merge.right, Some(merge.height)));
+            stack.push(Token::Text(",".to_string()));
+            stack.push(Token::Node(merge.left, Some(merge.height)));
+          }
         }
+      }
+    }
+    newick + ";\n"
+  }
+
+  fn to_text(&self, coordinates: &[Coordinate]) -> String {
+    const MAX_INDENT: usize = 32;
+    let mut text = String::new();
+    let mut stack = self.root().map(|root| (root, 0)).into_iter().collect::<Vec<_>>();
+    while let Some((node, depth)) = stack.pop() {
+      // Deep chains would indent quadratically, past a point the depth is written out instead
+      let indent = match depth {
+        0..=MAX_INDENT => "  ".repeat(depth),
+        _ => format!("{}[{}] ", "  ".repeat(MAX_INDENT), depth),
       };
+      if node < self.leaves {
+        text += &format!("{}n{} {}\n", indent, node, coordinates[node]);
+      } else {
+        let merge = &self.merges[node - self.leaves];
+        text += &format!("{}{:.3} ({} boxes)\n", indent, merge.height, merge.size);
+        stack.push((merge.right, depth + 1));
+        stack.push((merge.left, depth + 1));
+      }
+    }
+    text
+  }
+
+  fn write(&self, coordinates: &[Coordinate], path: &str) -> Result<()> {
+    let contents = match path.rsplit_once('.').map(|(_, ext)| ext) {
+      Some("nwk" | "newick") => self.to_newick(),
+      Some("txt") => self.to_text(coordinates),
+      _ => {
+        return Err(anyhow!(
+          "Can't tell the format of {}, use a .nwk or .txt file",
+          path
+        ));
+      }
+    };
+    fs::write(path, contents)?;
+    Ok(())
+  }
 
-      let mut link_set = HashSet::new();
-      all_linked(c, &links, &mut link_set);
+  // The clusters left after the first `merges` merges, each sorted and ordered by first box
+  fn clusters(&self, merges: usize) -> Vec<Vec<usize>> {
+    let mut clusters = DisjointSet::new(self.leaves);
+    for merge in &self.merges[..merges] {
+      clusters.union(merge.a, merge.b);
+    }
+    let mut members = vec![Vec::new(); self.leaves];
+    for ix in 0..self.leaves {
+      members[clusters.find(ix)].push(ix);
+    }
+    let mut members = members.into_iter().filter(|m| !m.is_empty()).collect::<Vec<_>>();
+    members.sort();
+    members
+  }
 
-      for linked in link_set {
-        // println!("{:?}, {}", linked, group_id);
-        groups[group_id].insert(linked);
-        belonging.insert(linked, group_id);
+  // Clusters whose boxes are joined by links no longer than `threshold`
+  fn cut_at(&self, threshold: f64) -> Vec<Vec<usize>> {
+    self.clusters(self.merges.partition_point(|m| m.height <= threshold))
+  }
+
+  fn cut_into(&self, count: usize) -> Result<Vec<Vec<usize>>> {
+    if count == 0 || count > self.leaves {
+      return Err(anyhow!(
+        "Can't cut {} boxes into {} clusters",
+        self.leaves,
+        count
+      ));
+    }
+    Ok(self.clusters(self.leaves - count))
+  }
+}
+
+const PARTS: [&str; 3] = ["pt1", "pt2", "cluster"];
+const OPTIONS: [&str; 8] = [
+  "--links=",
+  "--top=",
+  "--metric=",
+  "--weights=",
+  "--mst=",
+  "--dendrogram=",
+  "--distance=",
+  "--clusters=",
+];
+
+// Anything that isn't the part or one of the options is refused, a typo in an option name or the
+// old positional link count would otherwise run with the defaults and print a wrong answer.
+fn check_args(args: &[String]) -> Result<()> {
+  for (ix, arg) in args.iter().enumerate().skip(1) {
+    if ix == 1 && PARTS.contains(&arg.as_str()) {
+      continue;
+    }
+    if OPTIONS.iter().any(|o| arg.starts_with(o)) {
+      continue;
+    }
+    return Err(match arg.parse::<usize>() {
+      Ok(n) => anyhow!(
+        "Unexpected argument {}, the number of links is given as --links={}",
+        n,
+        n
+      ),
+      Err(_) if ix == 1 && !arg.starts_with("--") => {
+        anyhow!("Unknown part {}, try {}", arg, PARTS.join(", "))
       }
+      Err(_) => anyhow!(
+        "Unknown argument {}, options are {}",
+        arg,
+        OPTIONS.join(" ")
+      ),
+    });
+  }
+  Ok(())
+}
+
+// A positive count given as `name=value`, or the default when it's missing
+fn option(args: &[String], name: &str, default: usize) -> Result<usize> {
+  match args.iter().find_map(|a| a.strip_prefix(name)) {
+    Some(value) => match value.parse::<usize>() {
+      Ok(0) => Err(anyhow!("{}{} has to be at least 1", name, value)),
+      Ok(n) => Ok(n),
+      Err(e) => Err(anyhow!("Bad value for {}{}: {}", name, value, e)),
+    },
+    None => Ok(default),
+  }
+}
+
+fn solve<M: Metric>(
+  coordinates: &[Coordinate],
+  metric: M,
+  solution_part: &str,
+  args: &[String],
+) -> Result<()> {
+  check_metric(&metric, coordinates)?;
+  if solution_part == "pt1" {
+    let links = option(args, "--links=", 10)?;
+    let top = option(args, "--top=", 3)?;
+    let pairs = coordinates.len() * coordinates.len().saturating_sub(1) / 2;
+    if links > pairs {
+      return Err(anyhow!(
+        "Asked for {} links but {} boxes only make {} pairs",
+        links,
+        coordinates.len(),
+        pairs
+      ));
+    }
+
+    let mut circuits = DisjointSet::new(coordinates.len());
+    for (_, ix, jx) in NearestPairs::new(coordinates, &metric).take(links) {
+      circuits.union(ix, jx);
     }
-    let mut sizes = groups.iter().map(|g| g.len()).collect::<Vec<_>>();
-    sizes.sort();
+
+    let mut sizes = circuits.component_sizes();
+    sizes.sort_by(|a, b| b.cmp(a));
     println!("sizes {:?}", sizes);
-    let solution = sizes.iter().rev().take(3).product::<usize>();
+    for (size, group) in &sizes.iter().chunk_by(|&&size| size) {
+      println!("{} circuits of size {}", group.count(), size);
+    }
+    if top > sizes.len() {
+      return Err(anyhow!(
+        "Asked for the top {} circuits but there are only {}",
+        top,
+        sizes.len()
+      ));
+    }
+    let solution = sizes.iter().take(top).product::<usize>();
 
     println!("{}", solution);
+  } else if solution_part == "cluster" {
+    let dendrogram = Dendrogram::new(&SpanningTree::new(coordinates, &metric), coordinates.len());
+    if let Some(path) = args.iter().find_map(|a| a.strip_prefix("--dendrogram=")) {
+      dendrogram.write(coordinates, path)?;
+    }
+    let clusters = match args.iter().find_map(|a| a.strip_prefix("--distance=")) {
+      Some(threshold) => dendrogram.cut_at(
+        threshold.parse().map_err(|e| anyhow!("Bad value for --distance={}: {}", threshold, e))?,
+      ),
+      None => dendrogram.cut_into(option(args, "--clusters=", 1)?)?,
+    };
+    for cluster in &clusters {
+      println!("{}: {}", cluster.len(), cluster.iter().join(" "));
+    }
+
+    println!("{}", clusters.len());
   } else {
-    let mut groups: Vec<HashSet<Coordinate>> = vec![];
-    let mut belonging: HashMap<Coordinate, usize> = HashMap::new();
-
-    let mut current_pair = potential_links[0];
-    for (ix, (c1, c2)) in potential_links.iter().enumerate() {
-      current_pair = (*c1, *c2);
-      let current_ids = { (belonging.get(c1).map(|s| *s), belonging.get(c2).map(|s| *s)) };
-      let current_len = match current_ids {
-        (Some(id1), Some(id2)) if id1 == id2 => groups[id1].len(),
-        (Some(id1), Some(id2)) => {
-          let min_id = min(id1, id2);
-          let max_id = max(id1, id2);
-          groups[min_id] = groups[min_id].union(&groups[max_id]).map(|c| *c).collect();
-          for item in groups[max_id].iter() {
-            belonging.insert(*item, min_id);
-          }
-          //groups[max_id] = HashSet::new();
-          groups[min_id].len()
-        }
-        (Some(id), _) => {
-          groups[id].insert(**c2);
-          belonging.insert(**c2, id);
-          groups[id].len()
-        }
-        (_, Some(id)) => {
-          groups[id].insert(**c1);
-          belonging.insert(**c1, id);
-          groups[id].len()
-        }
-        (None, None) => {
-          let hs = HashSet::from_iter(vec![**c1, **c2].into_iter());
-          let hslen = hs.len();
-          groups.push(hs);
-          let id = groups.len() - 1;
-          belonging.insert(**c1, id);
-          hslen
-        }
-      };
-      println!("Got {} at link {}: {:?}", current_len, ix, current_pair);
-      if current_len == coordinates.len() {
-        break;
-      }
+    let tree = SpanningTree::new(coordinates, &metric);
+    if let Some(path) = args.iter().find_map(|a| a.strip_prefix("--mst=")) {
+      tree.write(coordinates, path)?;
+    }
+    for link in &tree.links {
+      let (a, b) = (&coordinates[link.a], &coordinates[link.b]);
+      println!("Got {} at link {}: {} - {}", link.size, link.rank, a, b);
     }
+    println!("Total length {:.3}", tree.total_length());
 
-    println!("{:?}", current_pair.0.x * current_pair.1.x);
+    let link = tree.final_link().ok_or(anyhow!("Need at least two junction boxes"))?;
+    let (c1, c2) = (&coordinates[link.a], &coordinates[link.b]);
+    println!("{:?}", c1.axis(0) * c2.axis(0));
   }
 
   Ok(())
 }
+
+fn main() -> Result<()> {
+  let mut data = String::new();
+  io::stdin().read_to_string(&mut data)?;
+  let coordinates = problem::coordinate_list(&data)?;
+  let dimensions = coordinates.first().map_or(0, Coordinate::dimensions);
+  if let Some((line, c)) =
+    coordinates.iter().enumerate().find(|(_, c)| c.dimensions() != dimensions)
+  {
+    return Err(anyhow!(
+      "Junction box on line {} has {} axes but the first one has {}",
+      line + 1,
+      c.dimensions(),
+      dimensions
+    ));
+  }
+
+  // Options: --links=N boxes to join in part 1 (10), --top=K circuits to multiply (3),
+  // --metric=euclidean|manhattan|chebyshev, --weights=w,... one per axis and --mst=file.dot|csv
+  // for part 2. The cluster part takes --dendrogram=file.nwk|txt and cuts the single-linkage
+  // clustering at --distance=D or into --clusters=K
+  let args: Vec<String> = env::args().collect();
+  check_args(&args)?;
+  let solution_part =
+    args.get(1).map(|x| x.as_str()).filter(|x| PARTS.contains(x)).unwrap_or("pt1");
+  let metric = args.iter().find_map(|a| a.strip_prefix("--metric=")).unwrap_or("euclidean");
+  let weights = match args.iter().find_map(|a| a.strip_prefix("--weights=")) {
+    Some(w) => w
+      .split(',')
+      .map(|x| x.parse::<u64>().map_err(|e| anyhow!("Bad weight {:?}: {}", x, e)))
+      .collect::<Result<Vec<_>>>()?,
+    None => vec![1; dimensions],
+  };
+  if weights.len() != dimensions {
+    return Err(anyhow!(
+      "Need a weight for each of the {} axes, got {}",
+      dimensions,
+      weights.len()
+    ));
+  }
+
+  match metric {
+    "euclidean" => solve(&coordinates, Euclidean { weights }, solution_part, &args),
+    "manhattan" => solve(&coordinates, Manhattan { weights }, solution_part, &args),
+    "chebyshev" => solve(&coordinates, Chebyshev { weights }, solution_part, &args),
+    _ => Err(anyhow!(
+      "Unknown metric {}, try euclidean, manhattan or chebyshev",
+      metric
+    )),
+  }
+}
 // 1000 too low
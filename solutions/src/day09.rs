@@ -1,4 +1,5 @@
 use anyhow::{Result, anyhow};
+use aoc_2025::grid;
 use itertools::Itertools;
 use std::cmp::max;
 use std::cmp::min;
@@ -9,7 +10,7 @@ use rayon::prelude::*;
 use std::env;
 use std::ops::RangeInclusive;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Coord {
   x: i64,
   y: i64,
@@ -35,26 +36,6 @@ enum Fill {
   Empty,
 }
 
-fn flood(a: &mut Vec<Vec<Fill>>, x: usize, y: usize, f: Fill) {
-  if y >= a.len() {
-    return;
-  }
-  if x >= a[y].len() {
-    return;
-  }
-  if a[y][x] == Fill::None {
-    a[y][x] = f;
-    flood(a, x + 1, y, f);
-    flood(a, x, y + 1, f);
-    if x > 0 {
-      flood(a, x - 1, y, f);
-    }
-    if y > 0 {
-      flood(a, x, y - 1, f);
-    }
-  }
-}
-
 // fn show(a: &Vec<Vec<Fill>>) {
 //   for y in 0..a.len() {
 //     for x in 0..a[y].len() {
@@ -73,117 +54,343 @@ fn rng<T: Ord + Copy>(one: T, two: T) -> RangeInclusive<T> {
   min(one, two)..=max(one, two)
 }
 
-fn main() -> Result<()> {
-  let args: Vec<String> = env::args().collect();
-  let solution_part = args.get(1).map(|x| x.as_str()).unwrap_or("pt1");
+// Answers whether the rectangle spanned by two red tiles only covers red or green tiles
+trait Containment: Sync {
+  fn contains(&self, c1: &Coord, c2: &Coord) -> bool;
+  // Red and green tiles in the whole loop
+  fn total(&self) -> i64;
+}
 
-  let coords =
-    io::stdin().lines().map(|line| line?.as_str().try_into()).collect::<Result<Vec<Coord>>>()?;
+fn area(c1: &Coord, c2: &Coord) -> i64 {
+  ((c1.x - c2.x).abs() + 1) * ((c1.y - c2.y).abs() + 1)
+}
 
-  if solution_part == "pt1" {
-    let area_max = coords
-      .iter()
-      .enumerate()
-      .flat_map(|(i, c1)| coords.iter().skip(i + 1).map(|c2| (c1, c2)).collect::<Vec<_>>())
-      .map(|(c1, c2)| ((c1.x - c2.x).abs() + 1) * ((c1.y - c2.y).abs() + 1))
-      .max()
-      .ok_or(anyhow!("No coordinates found"))?;
+// Every unique value gets a position in the tiny coordinate system, in order. When two values
+// are more than one tile apart, the tiles between them get a position of their own, otherwise a
+// notch one tile wide would vanish from the map. Position 0 is left free as a border.
+// Also returns how many real tiles every position stands for, the borders stand for none.
+fn compress(values: impl Iterator<Item = i64>) -> (HashMap<i64, usize>, Vec<i64>) {
+  let mut tiny = HashMap::new();
+  let mut sizes = vec![0];
+  let mut previous = None;
+  for value in values.unique().sorted() {
+    if let Some(gap) = previous.map(|p| value - p - 1).filter(|&gap| gap > 0) {
+      sizes.push(gap);
+    }
+    tiny.insert(value, sizes.len());
+    sizes.push(1);
+    previous = Some(value);
+  }
+  sizes.push(0);
+  (tiny, sizes)
+}
 
-    println!("{}", area_max);
-  } else {
+struct TinyMap {
+  tiny_x: HashMap<i64, usize>,
+  tiny_y: HashMap<i64, usize>,
+  // Summed-area table of red and green tiles: inside[y][x] counts the real tiles in every
+  // position left of x and above y
+  inside: Vec<Vec<i64>>,
+}
+
+impl TinyMap {
+  fn new(coords: &[Coord]) -> Result<TinyMap> {
     // Lets make a tiny map from the big map!
     // Every unique x of a red tile is a new position in x coordinates
     // Similarly, every unique y is a new position in y coordinates
     // We offset by 1 to leave spaces around the border of the new map.
-    let tiny_x = coords
-      .iter()
-      .map(|c| c.x)
-      .unique()
-      .sorted()
-      .enumerate()
-      .map(|(ix, x)| (x, ix + 1))
-      .collect::<HashMap<_, _>>();
-    let tiny_y = coords
-      .iter()
-      .map(|c| c.y)
-      .unique()
-      .sorted()
-      .enumerate()
-      .map(|(ix, x)| (x, ix + 1))
-      .collect::<HashMap<_, _>>();
-
-    let max_x = tiny_x.values().max().unwrap();
-    let max_y = tiny_y.values().max().unwrap();
+    let (tiny_x, widths) = compress(coords.iter().map(|c| c.x));
+    let (tiny_y, heights) = compress(coords.iter().map(|c| c.y));
 
     // Translate the coordinates to the tiny coordinate system
-    let tiny_coords = coords
-      .iter()
-      .map(|c| (tiny_x.get(&c.x).unwrap(), tiny_y.get(&c.y).unwrap()))
-      .collect::<Vec<_>>();
+    let tiny_coords = coords.iter().map(|c| (tiny_x[&c.x], tiny_y[&c.y])).collect::<Vec<_>>();
 
     // Make a cute tiny map!
-    // In my testing this turns out to be around 250x250, no issues with that size
-    let mut tiny_map =
-      (0..*max_y + 2).map(|_| vec![Fill::None; *max_x as usize + 2]).collect::<Vec<_>>();
+    // In my testing this turns out to be around 250x250, the gap cells can double that
+    let mut map = (0..heights.len()).map(|_| vec![Fill::None; widths.len()]).collect::<Vec<_>>();
 
     // Prepare to draw the tiny lines.
     let lines =
       tiny_coords.iter().zip(tiny_coords.iter().skip(1).chain(tiny_coords.iter().take(1)));
 
     // Fill the lines
-    for ((sx, sy), (ex, ey)) in lines {
+    for (&(sx, sy), &(ex, ey)) in lines {
       if sx == ex {
-        for y in rng(**sy, **ey) {
-          tiny_map[y][**sx] = Fill::Filled
+        for y in rng(sy, ey) {
+          map[y][sx] = Fill::Filled
         }
       } else if sy == ey {
-        for x in rng(**sx, **ex) {
-          tiny_map[**sy][x] = Fill::Filled
+        for x in rng(sx, ex) {
+          map[sy][x] = Fill::Filled
         }
       } else {
-        // We assume all lines are along the x axis or the y axis, panic if not
-        panic!(
+        // We assume all lines are along the x axis or the y axis
+        return Err(anyhow!(
           "Unexpected coordinate alignment {} {}; {} {}",
-          sx, sy, ex, ey
-        );
+          sx,
+          sy,
+          ex,
+          ey
+        ));
       }
     }
-    //show(&tiny_map);
+    //show(&map);
 
     // Flood-fill from the top left corner with emptyness. The flood stops at the drawn borders
     // Thanks to our slightly larger map, it should be able to go around the edges and reach every
     // bit of empty space.
-    flood(&mut tiny_map, 0, 0, Fill::Empty);
-    //show(&tiny_map);
+    grid::flood(&mut map, 0, 0, Fill::None, Fill::Empty);
+    //show(&map);
 
-    // For every possible pair of red coordinates
-    let area_max = coords
-      .par_iter()
-      .enumerate()
-      .flat_map(|(i, c1)| coords.iter().skip(i + 1).map(|c2| (c1, c2)).collect::<Vec<_>>())
-      .filter(|(c1, c2)| {
-        // can we use this pair? translate to tiny coordinate system
-        let tx1 = tiny_x.get(&c1.x).unwrap();
-        let tx2 = tiny_x.get(&c2.x).unwrap();
-        let ty1 = tiny_y.get(&c1.y).unwrap();
-        let ty2 = tiny_y.get(&c2.y).unwrap();
-
-        // then check if the entire area of this rectangle is non-empty
-        // if a single tile was reached by the flood-fill, early return.
-        for y in rng(*ty1, *ty2) {
-          for x in rng(*tx1, *tx2) {
-            if tiny_map[y][x] == Fill::Empty {
-              return false;
+    // Everything the flood didn't reach is red or green, as many tiles as the position covers
+    let mut inside = vec![vec![0; widths.len() + 1]; heights.len() + 1];
+    for (y, row) in map.iter().enumerate() {
+      for (x, fill) in row.iter().enumerate() {
+        let tiles = if *fill == Fill::Empty {
+          0
+        } else {
+          widths[x] * heights[y]
+        };
+        inside[y + 1][x + 1] = tiles + inside[y][x + 1] + inside[y + 1][x] - inside[y][x];
+      }
+    }
+
+    Ok(TinyMap {
+      tiny_x,
+      tiny_y,
+      inside,
+    })
+  }
+}
+
+impl TinyMap {
+  // Red and green tiles in the rectangle between two red tiles
+  fn enclosed(&self, c1: &Coord, c2: &Coord) -> i64 {
+    // translate to tiny coordinate system
+    let (tx1, tx2) = (self.tiny_x[&c1.x], self.tiny_x[&c2.x]);
+    let (ty1, ty2) = (self.tiny_y[&c1.y], self.tiny_y[&c2.y]);
+    let (x1, x2) = (min(tx1, tx2), max(tx1, tx2) + 1);
+    let (y1, y2) = (min(ty1, ty2), max(ty1, ty2) + 1);
+    self.inside[y2][x2] - self.inside[y1][x2] - self.inside[y2][x1] + self.inside[y1][x1]
+  }
+}
+
+impl Containment for TinyMap {
+  fn contains(&self, c1: &Coord, c2: &Coord) -> bool {
+    // The rectangle fits if every tile in it is red or green
+    self.enclosed(c1, c2) == area(c1, c2)
+  }
+
+  fn total(&self) -> i64 {
+    self.inside.last().and_then(|row| row.last()).copied().unwrap_or(0)
+  }
+}
+
+// The loop of red and green tiles as a shape made of whole tiles, checked without drawing
+// anything. Coordinates are doubled so tile (x, y) covers the square from 2x-1 to 2x+1 and every
+// border lies on an odd coordinate. A rectangle fits when no border passes through its inside and
+// its centre is inside the shape.
+struct Polygon {
+  // (x, y1, y2) with y1 < y2
+  vertical: Vec<(i64, i64, i64)>,
+  // (y, x1, x2) with x1 < x2
+  horizontal: Vec<(i64, i64, i64)>,
+  tiles: i64,
+}
+
+impl Polygon {
+  fn new(coords: &[Coord]) -> Result<Polygon> {
+    let mut corners = coords.to_vec();
+    corners.dedup();
+    if corners.len() > 1 && corners.first() == corners.last() {
+      corners.pop();
+    }
+    let n = corners.len();
+    if let Some((s, e)) =
+      (0..n).map(|ix| (corners[ix], corners[(ix + 1) % n])).find(|(s, e)| s.x != e.x && s.y != e.y)
+    {
+      return Err(anyhow!(
+        "Unexpected coordinate alignment {} {}; {} {}",
+        s.x,
+        s.y,
+        e.x,
+        e.y
+      ));
+    }
+
+    // Walk the loop counter-clockwise, the outside of every edge is then on its right
+    let twice_area = (0..n)
+      .map(|ix| {
+        let (a, b) = (corners[ix], corners[(ix + 1) % n]);
+        a.x * b.y - b.x * a.y
+      })
+      .sum::<i64>();
+    if twice_area < 0 {
+      corners.reverse();
+    }
+    // Pick's theorem counts the tile centres inside and on the loop from its area and length
+    let perimeter = (0..n)
+      .map(|ix| {
+        let (a, b) = (corners[ix], corners[(ix + 1) % n]);
+        (a.x - b.x).abs() + (a.y - b.y).abs()
+      })
+      .sum::<i64>();
+    let tiles = twice_area.abs() / 2 + perimeter / 2 + 1;
+    let outward = |a: &Coord, b: &Coord| Coord {
+      x: (b.y - a.y).signum(),
+      y: (a.x - b.x).signum(),
+    };
+
+    // Push every edge half a tile outwards. Each corner moves along both of its edges, or just
+    // the one when it sits in the middle of a straight line.
+    let moved = (0..n)
+      .map(|ix| {
+        let (prev, c, next) = (
+          corners[(ix + n - 1) % n],
+          corners[ix],
+          corners[(ix + 1) % n],
+        );
+        let (n1, n2) = (outward(&prev, &c), outward(&c, &next));
+        let (dx, dy) = if n1 == n2 {
+          (n1.x, n1.y)
+        } else {
+          (n1.x + n2.x, n1.y + n2.y)
+        };
+        Coord {
+          x: c.x * 2 + dx,
+          y: c.y * 2 + dy,
+        }
+      })
+      .collect::<Vec<_>>();
+
+    // Two parallel edges one tile apart move onto the same line going opposite ways. The tiles
+    // on both sides are red or green, so the overlap isn't a border and both sides cancel out.
+    let mut vertical = HashMap::<i64, Vec<(i64, i64)>>::new();
+    let mut horizontal = HashMap::<i64, Vec<(i64, i64)>>::new();
+    for (s, e) in moved.iter().zip(moved.iter().cycle().skip(1)) {
+      if s.x == e.x {
+        vertical.entry(s.x).or_default().extend([(s.y, 1), (e.y, -1)]);
+      } else {
+        horizontal.entry(s.y).or_default().extend([(s.x, 1), (e.x, -1)]);
+      }
+    }
+    let borders = |lines: HashMap<i64, Vec<(i64, i64)>>| {
+      let mut segments = Vec::new();
+      for (at, mut events) in lines {
+        events.sort();
+        let mut depth = 0;
+        let mut start = None;
+        for (ix, &(pos, step)) in events.iter().enumerate() {
+          depth += step;
+          // Only look at the depth once every event at this position is counted
+          if events.get(ix + 1).is_some_and(|&(next, _)| next == pos) {
+            continue;
+          }
+          match (start, depth != 0) {
+            (None, true) => start = Some(pos),
+            (Some(from), false) => {
+              segments.push((at, from, pos));
+              start = None;
             }
+            _ => (),
           }
         }
-        return true;
-      })
-      //.inspect(|cs| println!("{:?}", cs))
+      }
+      segments
+    };
+
+    Ok(Polygon {
+      vertical: borders(vertical),
+      horizontal: borders(horizontal),
+      tiles,
+    })
+  }
+
+  // Inside, for points off the borders, by casting a ray towards +x and counting the borders it
+  // crosses. Half open ranges make a ray through a corner count once.
+  fn inside(&self, p: &Coord) -> bool {
+    let crossings =
+      self.vertical.iter().filter(|&&(x, y1, y2)| x > p.x && y1 <= p.y && p.y < y2).count();
+    crossings % 2 == 1
+  }
+
+  // Whether any border touches the open rectangle between the corners
+  fn crosses(&self, lo: &Coord, hi: &Coord) -> bool {
+    self.vertical.iter().any(|&(x, y1, y2)| lo.x < x && x < hi.x && y1 < hi.y && y2 > lo.y)
+      || self.horizontal.iter().any(|&(y, x1, x2)| lo.y < y && y < hi.y && x1 < hi.x && x2 > lo.x)
+  }
+
+  // The corners of the squares of every tile in the rectangle between two tiles
+  fn squares(c1: &Coord, c2: &Coord) -> (Coord, Coord) {
+    let lo = Coord {
+      x: min(c1.x, c2.x) * 2 - 1,
+      y: min(c1.y, c2.y) * 2 - 1,
+    };
+    let hi = Coord {
+      x: max(c1.x, c2.x) * 2 + 1,
+      y: max(c1.y, c2.y) * 2 + 1,
+    };
+    (lo, hi)
+  }
+}
+
+impl Containment for Polygon {
+  fn contains(&self, c1: &Coord, c2: &Coord) -> bool {
+    let (lo, hi) = Polygon::squares(c1, c2);
+    let centre = Coord {
+      x: (lo.x + hi.x) / 2,
+      y: (lo.y + hi.y) / 2,
+    };
+    !self.crosses(&lo, &hi) && self.inside(&centre)
+  }
+
+  fn total(&self) -> i64 {
+    self.tiles
+  }
+}
+
+fn largest<C: Containment + ?Sized>(coords: &[Coord], engine: &C) -> Result<i64> {
+  // For every possible pair of red coordinates
+  coords
+    .par_iter()
+    .enumerate()
+    .flat_map(|(i, c1)| coords.iter().skip(i + 1).map(|c2| (c1, c2)).collect::<Vec<_>>())
+    .filter(|(c1, c2)| engine.contains(c1, c2))
+    //.inspect(|cs| println!("{:?}", cs))
+    .map(|(c1, c2)| area(c1, c2))
+    .max()
+    .ok_or(anyhow!("No coordinates found"))
+}
+
+fn main() -> Result<()> {
+  let args: Vec<String> = env::args().collect();
+  let solution_part = args.get(1).map(|x| x.as_str()).unwrap_or("pt1");
+
+  let coords =
+    io::stdin().lines().map(|line| line?.as_str().try_into()).collect::<Result<Vec<Coord>>>()?;
+
+  if solution_part == "pt1" {
+    let area_max = coords
+      .iter()
+      .enumerate()
+      .flat_map(|(i, c1)| coords.iter().skip(i + 1).map(|c2| (c1, c2)).collect::<Vec<_>>())
       .map(|(c1, c2)| ((c1.x - c2.x).abs() + 1) * ((c1.y - c2.y).abs() + 1))
       .max()
       .ok_or(anyhow!("No coordinates found"))?;
 
+    println!("{}", area_max);
+  } else {
+    // The flood fill counts tiles like the puzzle does, the geometric engine checks the shape
+    let engine: Box<dyn Containment> = match args.iter().find_map(|a| a.strip_prefix("--engine=")) {
+      None | Some("flood") => Box::new(TinyMap::new(&coords)?),
+      Some("geometric") => Box::new(Polygon::new(&coords)?),
+      Some(engine) => return Err(anyhow!("Unknown engine {}, try flood or geometric", engine)),
+    };
+    if args.iter().any(|a| a == "--enclosed") {
+      println!("Enclosed area {}", engine.total());
+    }
+    let area_max = largest(&coords, engine.as_ref())?;
+
     println!("{:?}", area_max);
   }
 
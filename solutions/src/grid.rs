@@ -0,0 +1,33 @@
+// Replaces the area of `target` cells connected to (x, y) with `fill`, moving up, down, left and
+// right, and returns how many cells changed. Pending cells live on a heap allocated stack, so
+// large open areas don't run out of call stack like a recursive fill would.
+pub fn flood<T: Copy + PartialEq>(
+  grid: &mut [Vec<T>],
+  x: usize,
+  y: usize,
+  target: T,
+  fill: T,
+) -> usize {
+  if target == fill {
+    return 0;
+  }
+
+  let mut filled = 0;
+  let mut pending = vec![(x, y)];
+  while let Some((x, y)) = pending.pop() {
+    match grid.get_mut(y).and_then(|row| row.get_mut(x)) {
+      Some(cell) if *cell == target => *cell = fill,
+      _ => continue,
+    }
+    filled += 1;
+    pending.push((x + 1, y));
+    pending.push((x, y + 1));
+    if x > 0 {
+      pending.push((x - 1, y));
+    }
+    if y > 0 {
+      pending.push((x, y - 1));
+    }
+  }
+  filled
+}
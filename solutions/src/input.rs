@@ -0,0 +1,44 @@
+use anyhow::Result;
+use std::io::{self, Read};
+
+// Puzzle inputs get copy-pasted between machines and editors, so before any grammar sees them:
+// - \r\n and lone \r line endings become \n
+// - trailing spaces and tabs are removed from every line
+// - blank lines before the first line with content are dropped
+// - the input ends with exactly one \n (or is empty)
+// Leading whitespace is kept, some days (day06) depend on column positions.
+pub fn normalize(raw: &str) -> String {
+  let unified = raw.replace("\r\n", "\n").replace('\r', "\n");
+  let lines = unified
+    .split('\n')
+    .map(|l| l.trim_end_matches([' ', '\t']))
+    .skip_while(|l| l.is_empty())
+    .collect::<Vec<_>>();
+
+  let content_len = lines.iter().rposition(|l| !l.is_empty()).map_or(0, |ix| ix + 1);
+  lines[..content_len].iter().map(|l| format!("{}\n", l)).collect()
+}
+
+// Removes everything from `marker` to the end of each line. Lines that only held a comment are
+// dropped entirely so they don't turn into blank separator lines. The result is normalized again,
+// a comment header followed by a blank line would otherwise leave a leading blank line.
+// Not part of normalize since some inputs use # as data (day12's presents).
+pub fn strip_comments(data: &str, marker: &str) -> String {
+  let stripped = data
+    .lines()
+    .filter_map(|l| match l.split_once(marker) {
+      Some((before, _)) if before.trim().is_empty() => None,
+      Some((before, _)) => Some(before.trim_end()),
+      None => Some(l),
+    })
+    .map(|l| format!("{}\n", l))
+    .collect::<String>();
+
+  normalize(&stripped)
+}
+
+pub fn read_stdin() -> Result<String> {
+  let mut data = String::new();
+  io::stdin().read_to_string(&mut data)?;
+  Ok(normalize(&data))
+}
@@ -0,0 +1,39 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs one of the day binaries the same way we do by hand: input on stdin, everything it prints
+// captured.
+fn output(bin: &str, args: &[&str], input: &str) -> Output {
+  let mut child = Command::new(bin)
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .expect("Cant start binary");
+
+  child.stdin.take().unwrap().write_all(input.as_bytes()).expect("Cant write stdin");
+  child.wait_with_output().expect("Binary did not finish")
+}
+
+// Runs a day binary that is expected to succeed and returns its answer from stdout.
+pub fn run(bin: &str, args: &[&str], input: &str) -> String {
+  let output = output(bin, args, input);
+  assert!(
+    output.status.success(),
+    "{}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+
+  String::from_utf8(output.stdout).expect("Output is not utf8")
+}
+
+// Runs a day binary that is expected to fail and returns what it printed on stderr.
+// Not every test crate checks errors.
+#[allow(dead_code)]
+pub fn run_err(bin: &str, args: &[&str], input: &str) -> String {
+  let output = output(bin, args, input);
+  assert!(!output.status.success(), "Binary succeeded unexpectedly");
+
+  String::from_utf8(output.stderr).expect("Output is not utf8")
+}
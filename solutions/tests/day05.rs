@@ -0,0 +1,81 @@
+mod common;
+
+use proptest::prelude::*;
+use std::collections::HashSet;
+
+fn pt2(ranges: &[(u64, u64)]) -> String {
+  let input = ranges.iter().map(|(s, e)| format!("{}-{}\n", s, e)).collect::<String>() + "\n";
+  common::run(env!("CARGO_BIN_EXE_day05"), &["pt2"], &input).trim().to_string()
+}
+
+#[test]
+fn example() {
+  assert_eq!(pt2(&[(3, 5), (10, 14), (16, 20), (12, 18)]), "14");
+}
+
+#[test]
+fn nested_and_touching() {
+  assert_eq!(pt2(&[(1, 10), (2, 3), (4, 4)]), "10");
+  assert_eq!(pt2(&[(1, 3), (4, 6), (7, 7)]), "7");
+}
+
+#[test]
+fn maximal_ranges() {
+  assert_eq!(pt2(&[(u64::MAX, u64::MAX)]), "1");
+  assert_eq!(
+    pt2(&[(u64::MAX - 4, u64::MAX), (u64::MAX - 2, u64::MAX)]),
+    "5"
+  );
+  assert_eq!(pt2(&[(0, u64::MAX), (5, 10)]), "18446744073709551616");
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(64))]
+
+  #[test]
+  fn matches_brute_force(ranges in prop::collection::vec((0u64..60, 0u64..60), 1..8)) {
+    let expected = ranges.iter().flat_map(|(s, e)| *s..=*e).collect::<HashSet<_>>().len();
+    prop_assert_eq!(pt2(&ranges), expected.to_string());
+  }
+}
+
+#[test]
+fn signed_and_wide_ids() {
+  let input = "-5-10\n-20--15\n5--3\n\n-7\n-16\n";
+  let run = |args: &[&str]| common::run(env!("CARGO_BIN_EXE_day05"), args, input);
+  assert_eq!(run(&["pt1", "--ids=i64"]).trim(), "1");
+  assert_eq!(run(&["pt2", "--ids=i64"]).trim(), "22");
+
+  let input = "0-340282366920938463463374607431768211454\n\n";
+  let out = common::run(env!("CARGO_BIN_EXE_day05"), &["pt2", "--ids=u128"], input);
+  assert_eq!(out.trim(), "340282366920938463463374607431768211455");
+}
+
+#[test]
+fn messy_input() {
+  let input = "# fresh ranges\r\n\r\n3-5  \r\n10-14 # second\r\n16-20\r\n12-18\r\n\r\n\r\n1\r\n5\r\n8\r\n11\r\n17\r\n32\t\r\n\r\n";
+  assert_eq!(
+    common::run(env!("CARGO_BIN_EXE_day05"), &["pt1"], input).trim(),
+    "3"
+  );
+}
+
+#[test]
+fn query_explains_ids() {
+  let input = "3-5\n10-14\n16-20\n12-18\n\n1\n5\n8\n17\n32\n";
+  let out = common::run(env!("CARGO_BIN_EXE_day05"), &["query"], input);
+  assert_eq!(
+    out.lines().collect::<Vec<_>>(),
+    [
+      "1: spoiled, nearest below none, nearest above 3-5",
+      "5: fresh, covered by 3-5; merged 3-5",
+      "8: spoiled, nearest below 3-5, nearest above 10-14",
+      "17: fresh, covered by 16-20, 12-18; merged 10-20",
+      "32: spoiled, nearest below 16-20, nearest above none",
+    ]
+  );
+
+  // Ids on the command line replace the ones in the input
+  let out = common::run(env!("CARGO_BIN_EXE_day05"), &["query", "11"], input);
+  assert_eq!(out.trim(), "11: fresh, covered by 10-14; merged 10-20");
+}
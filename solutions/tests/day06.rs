@@ -0,0 +1,109 @@
+mod common;
+
+fn run(args: &[&str], input: &str) -> String {
+  common::run(env!("CARGO_BIN_EXE_day06"), args, input)
+}
+
+fn strip_trailing(input: &str) -> String {
+  input.lines().map(|l| format!("{}\n", l.trim_end())).collect()
+}
+
+const EXAMPLE: &str = "123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n*   +   *   +  \n";
+
+// One left-aligned, one right-aligned, one ragged and one centred problem
+const ALIGNMENTS: &str = "\
+12   12 7    5 
+3     3  88 123
+456 456 9    4 
++   *   +   *  
+";
+
+#[test]
+fn example() {
+  assert_eq!(run(&["pt1"], EXAMPLE).trim(), "4277556");
+  assert_eq!(run(&["pt2"], EXAMPLE).trim(), "3263827");
+}
+
+#[test]
+fn example_without_trailing_whitespace() {
+  let stripped = strip_trailing(EXAMPLE);
+  assert_eq!(run(&["pt1"], &stripped).trim(), "4277556");
+  assert_eq!(run(&["pt2"], &stripped).trim(), "3263827");
+}
+
+#[test]
+fn mixed_alignments() {
+  // pt2: 6 + 25 + 134, 236 * 15 * 4, 8 + 8 + 79, 3 * 524 * 1
+  for input in [ALIGNMENTS.to_string(), strip_trailing(ALIGNMENTS)] {
+    assert_eq!(run(&["pt1"], &input).trim(), "19451");
+    assert_eq!(run(&["pt2"], &input).trim(), "15992");
+  }
+}
+
+#[test]
+fn detects_alignment() {
+  let shown = run(&["pt2", "--show"], &strip_trailing(ALIGNMENTS));
+  let headers = shown.lines().filter(|l| l.starts_with("Problem")).collect::<Vec<_>>();
+  assert_eq!(
+    headers,
+    vec![
+      "Problem 1 (columns 1-3), left-aligned",
+      "Problem 2 (columns 5-7), right-aligned",
+      "Problem 3 (columns 9-11), ragged",
+      "Problem 4 (columns 13-15), centred",
+    ]
+  );
+}
+
+fn run_err(args: &[&str], input: &str) -> String {
+  common::run_err(env!("CARGO_BIN_EXE_day06"), args, input)
+}
+
+#[test]
+fn other_operators() {
+  // 100 - 20 - 3, 120 / 3 / 2, max of 5 9 4, min of 7 1 8
+  let input = "100 120 5   7  \n 20   3 9   1  \n  3   2 4   8  \n-   /   max min\n";
+  assert_eq!(run(&["pt1"], input).trim(), "107");
+}
+
+#[test]
+fn operator_errors() {
+  assert!(run_err(&["pt1"], "1 2\n3 4\n% +\n").contains("Unknown operator %"));
+  assert!(run_err(&["pt1"], "5\n0\n/\n").contains("Division by zero in 5 / 0"));
+}
+
+#[test]
+fn overflow_and_big() {
+  let product = "18446744073709551615\n2\n*\n";
+  let err = run_err(&["pt1"], product);
+  assert!(err.contains("Problem 1 (columns 1-20)"));
+  assert!(err.contains("18446744073709551615 * 2 does not fit in a u64"));
+  assert_eq!(
+    run(&["pt1", "--big"], product).trim(),
+    "36893488147419103230"
+  );
+
+  let total = "18446744073709551615 1\n+                    +\n";
+  assert!(run_err(&["pt1"], total).contains("Grand total does not fit in a u64, try --big"));
+  assert_eq!(run(&["pt1", "--big"], total).trim(), "18446744073709551616");
+
+  // Cells past u64 only parse as big integers, where - can also go below zero
+  let wide = "18446744073709551616\n2\n*\n";
+  assert!(run_err(&["pt1"], wide).contains("Invalid number \"18446744073709551616\" for a u64"));
+  assert_eq!(run(&["pt1", "--big"], wide).trim(), "36893488147419103232");
+  assert_eq!(run(&["pt1", "--big"], "2\n5\n-\n").trim(), "-3");
+}
+
+#[test]
+fn big_without_a_part() {
+  assert_eq!(run(&["--big"], EXAMPLE).trim(), "4277556");
+}
+
+#[test]
+fn show_without_a_part() {
+  // Part 1 reads the rows, so the first problem shows 123 45 6 rather than 1 24 356
+  let shown = run(&["--show"], EXAMPLE);
+  assert_eq!(shown, run(&["pt1", "--show"], EXAMPLE));
+  assert!(shown.contains("= 123 * 45 * 6 = 33210"));
+  assert_eq!(shown.lines().last(), Some("4277556"));
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use std::{env, fs};
+
+fn run(part: &str, input: &str) -> String {
+  let out = common::run(env!("CARGO_BIN_EXE_day07"), &[part], input);
+  out.lines().last().unwrap_or("").to_string()
+}
+
+const EXAMPLE: &str = "\
+.......S.......
+...............
+.......^.......
+...............
+......^.^......
+...............
+.....^.^.^.....
+...............
+....^.^...^....
+...............
+...^.^...^.^...
+...............
+..^...^.....^..
+...............
+.^.^.^.^.^...^.
+...............
+";
+
+#[test]
+fn example() {
+  assert_eq!(run("pt1", EXAMPLE), "21");
+  assert_eq!(run("pt2", EXAMPLE), "40");
+}
+
+#[test]
+fn draws_beams_like_the_puzzle() {
+  let drawn = common::run(env!("CARGO_BIN_EXE_day07"), &["pt1", "--draw"], EXAMPLE);
+  let expected = "\
+.......S.......
+.......|.......
+......|^|......
+......|.|......
+.....|^|^|.....
+.....|.|.|.....
+....|^|^|^|....
+....|.|.|.|....
+...|^|^|||^|...
+...|.|.|||.|...
+..|^|^|||^|^|..
+..|.|.|||.|.|..
+.|^|||^||.||^|.
+.|.|||.||.||.|.
+|^|^|^|^|^|||^|
+|.|.|.|.|.|||.|
+21
+";
+  assert_eq!(drawn, expected);
+}
+
+#[test]
+fn splitter_on_first_column() {
+  let input = "S..\n^..\n...\n";
+  assert_eq!(run("pt1", input), "1");
+  assert_eq!(run("pt2", input), "1");
+}
+
+#[test]
+fn splitter_on_last_column() {
+  let input = "..S\n..^\n...\n";
+  assert_eq!(run("pt1", input), "1");
+  assert_eq!(run("pt2", input), "1");
+}
+
+#[test]
+fn edge_splitters_on_the_last_row() {
+  assert_eq!(run("pt1", "S..\n^..\n"), "1");
+  assert_eq!(run("pt2", "S..\n^..\n"), "1");
+  assert_eq!(run("pt2", "..S\n..^\n"), "1");
+  assert_eq!(run("pt2", ".S.\n.^.\n^.^\n"), "2");
+  assert_eq!(run("pt2", "S\n^\n"), "0");
+}
+
+#[test]
+fn beams_reaching_both_edges() {
+  let input = ".S.\n.^.\n^.^\n...\n";
+  assert_eq!(run("pt1", input), "3");
+  assert_eq!(run("pt2", input), "2");
+}
+
+#[test]
+fn mirrors_and_absorbers() {
+  // Down, right along row 1, back down into a splitter whose right beam leaves the manifold
+  let input = "S..\n\\.\\\n..^\n...\n";
+  assert_eq!(run("pt1", input), "1");
+  assert_eq!(run("pt2", input), "1");
+
+  assert_eq!(run("pt2", "S\n#\n.\n"), "0");
+  assert_eq!(run("pt2", ".S.\n.>.\n...\n"), "1");
+}
+
+#[test]
+fn timelines_beyond_u64() {
+  // A full triangle of splitters doubles the timelines on every level, 70 levels give 2^70
+  let width = 141;
+  let mut input = format!("{}S{}\n", ".".repeat(70), ".".repeat(70));
+  for level in 0..70usize {
+    let row = (0..width)
+      .map(|col: usize| {
+        if col % 2 == level % 2 && col.abs_diff(70) <= level {
+          '^'
+        } else {
+          '.'
+        }
+      })
+      .collect::<String>();
+    input += &format!("{}\n{}\n", row, ".".repeat(width));
+  }
+
+  let out = common::run(env!("CARGO_BIN_EXE_day07"), &["pt2", "--big"], &input);
+  assert_eq!(out.lines().last(), Some("1180591620717411303424"));
+}
+
+#[test]
+fn trailing_whitespace() {
+  let input = EXAMPLE.lines().map(|l| format!("{}  \r\n", l)).collect::<String>();
+  assert_eq!(run("pt1", &input), "21");
+  assert_eq!(run("pt2", &input), "40");
+}
+
+#[test]
+fn flags_without_a_part() {
+  let bin = env!("CARGO_BIN_EXE_day07");
+  let drawn = common::run(bin, &["--draw"], ".S.\n.^.\n...\n");
+  assert_eq!(drawn, ".S.\n|^|\n|.|\n1\n");
+  assert_eq!(common::run(bin, &["--big"], EXAMPLE).trim(), "21");
+}
+
+#[test]
+fn heatmap() {
+  let path = env::temp_dir().join(format!("day07-heatmap-{}.ppm", std::process::id()));
+  let arg = format!("--heatmap={}", path.display());
+  let out = common::run(env!("CARGO_BIN_EXE_day07"), &["pt2", &arg], EXAMPLE);
+  assert_eq!(out.trim(), "40");
+
+  // 15 columns and 16 rows, 4x4 pixels per cell, 3 bytes per pixel
+  let image = fs::read(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+  let header = b"P6\n60 64\n255\n";
+  assert!(image.starts_with(header));
+  assert_eq!(image.len(), header.len() + 60 * 64 * 3);
+  // The top left cell is empty space no beam reaches
+  assert_eq!(image[header.len()..header.len() + 3], [0, 0, 0]);
+}
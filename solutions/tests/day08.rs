@@ -0,0 +1,267 @@
+mod common;
+
+use proptest::prelude::*;
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+fn run(args: &[&str], input: &str) -> String {
+  let out = common::run(env!("CARGO_BIN_EXE_day08"), args, input);
+  out.lines().last().unwrap_or("").to_string()
+}
+
+const EXAMPLE: &str = "\
+162,817,812
+57,618,57
+906,360,560
+592,479,940
+352,342,300
+466,668,158
+542,29,236
+431,825,988
+739,650,466
+52,470,668
+216,146,977
+819,987,18
+117,168,530
+805,96,715
+346,949,466
+970,615,88
+941,993,340
+862,61,35
+984,92,344
+425,690,689
+";
+
+#[test]
+fn example() {
+  assert_eq!(run(&["pt1"], EXAMPLE), "40");
+  assert_eq!(run(&["pt2"], EXAMPLE), "25272");
+}
+
+#[test]
+fn component_histogram() {
+  let out = common::run(env!("CARGO_BIN_EXE_day08"), &["pt1", "--top=4"], EXAMPLE);
+  let lines = out.lines().collect::<Vec<_>>();
+  assert_eq!(
+    lines,
+    [
+      "sizes [5, 4, 2, 2, 1, 1, 1, 1, 1, 1, 1]",
+      "1 circuits of size 5",
+      "1 circuits of size 4",
+      "2 circuits of size 2",
+      "7 circuits of size 1",
+      "80",
+    ]
+  );
+  assert_eq!(run(&["pt1", "--links=190", "--top=1"], EXAMPLE), "20");
+}
+
+#[test]
+fn spanning_tree_csv() {
+  let path = env::temp_dir().join(format!("day08-mst-{}.csv", std::process::id()));
+  let flag = format!("--mst={}", path.display());
+  assert_eq!(run(&["pt2", &flag], EXAMPLE), "25272");
+
+  let csv = fs::read_to_string(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+  let rows = csv.lines().skip(1).collect::<Vec<_>>();
+  // A spanning tree over 20 boxes has 19 links, the last one joins everything
+  assert_eq!(rows.len(), 19);
+  assert_eq!(rows[0], "0,0,19,162,817,812,425,690,689,316.902,2");
+  assert!(rows[18].ends_with(",20"));
+  assert!(rows[18].contains(",216,146,977,117,168,530,"));
+}
+
+#[test]
+fn other_metrics() {
+  assert_eq!(
+    run(&["pt1", "--links=10", "--metric=manhattan"], EXAMPLE),
+    "36"
+  );
+  assert_eq!(run(&["pt2", "--metric=manhattan"], EXAMPLE), "452020");
+  assert_eq!(
+    run(&["pt1", "--links=10", "--metric=chebyshev"], EXAMPLE),
+    "60"
+  );
+  assert_eq!(run(&["pt2", "--metric=chebyshev"], EXAMPLE), "794430");
+  // Stretching z makes the last link a different pair
+  assert_eq!(run(&["pt2", "--weights=1,1,3"], EXAMPLE), "260128");
+}
+
+#[test]
+fn any_number_of_axes() {
+  assert_eq!(run(&["pt2"], "0,0\n3,4\n10,0\n10,1\n"), "30");
+  assert_eq!(run(&["pt2"], "0,0,0,0\n1,0,0,0\n5,5,5,5\n7,5,5,5\n"), "5");
+}
+
+#[test]
+fn dendrogram_cuts() {
+  assert_eq!(run(&["cluster", "--clusters=3"], EXAMPLE), "3");
+  let out = common::run(
+    env!("CARGO_BIN_EXE_day08"),
+    &["cluster", "--distance=322"],
+    EXAMPLE,
+  );
+  // Only the two shortest links are that short, both of them join box 0
+  assert_eq!(
+    out.lines().filter(|l| !l.starts_with("1:")).collect::<Vec<_>>(),
+    ["3: 0 7 19", "18"]
+  );
+
+  let path = env::temp_dir().join(format!("day08-dendrogram-{}.nwk", std::process::id()));
+  let flag = format!("--dendrogram={}", path.display());
+  assert_eq!(run(&["cluster", &flag], EXAMPLE), "1");
+  let newick = fs::read_to_string(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+  assert!(newick.starts_with("(n10:458.360,(n1:424.243,"));
+  assert!(newick.ends_with(");\n"));
+}
+
+// Every pair sorted by squared distance and then by input order, the way day08 first did it
+fn sorted_pairs(boxes: &[(i64, i64, i64)]) -> Vec<(usize, usize)> {
+  let distance = |a: (i64, i64, i64), b: (i64, i64, i64)| {
+    (a.0 - b.0).pow(2) + (a.1 - b.1).pow(2) + (a.2 - b.2).pow(2)
+  };
+  let mut pairs = (0..boxes.len())
+    .flat_map(|ix| ((ix + 1)..boxes.len()).map(move |jx| (ix, jx)))
+    .collect::<Vec<_>>();
+  pairs.sort_by_key(|&(ix, jx)| (distance(boxes[ix], boxes[jx]), ix, jx));
+  pairs
+}
+
+fn find(parent: &mut [usize], mut ix: usize) -> usize {
+  while parent[ix] != ix {
+    ix = parent[ix];
+  }
+  ix
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(64))]
+
+  // Coordinates from a tiny range so plenty of pairs tie on distance
+  #[test]
+  fn matches_sorted_pairs(
+    boxes in prop::collection::vec((0i64..4, 0i64..4, 0i64..4), 2..80),
+    links in 1usize..200,
+  ) {
+    let input = boxes.iter().map(|(x, y, z)| format!("{},{},{}\n", x, y, z)).collect::<String>();
+    let show = |ix: usize| format!("{},{},{}", boxes[ix].0, boxes[ix].1, boxes[ix].2);
+    let pairs = sorted_pairs(&boxes);
+    let links = links.min(pairs.len());
+
+    // Part 2 reports every link that joins two circuits, with its place in the order
+    let mut parent = (0..boxes.len()).collect::<Vec<_>>();
+    let mut size = vec![1; boxes.len()];
+    let mut merges = vec![];
+    let mut sizes_after = None;
+    for (rank, &(ix, jx)) in pairs.iter().enumerate() {
+      if rank == links {
+        let mut sizes = (0..boxes.len())
+          .filter(|&ix| find(&mut parent, ix) == ix)
+          .map(|ix| size[ix])
+          .collect::<Vec<_>>();
+        sizes.sort_by(|a, b| b.cmp(a));
+        sizes_after = Some(sizes);
+      }
+      let (ri, rj) = (find(&mut parent, ix), find(&mut parent, jx));
+      if ri != rj {
+        parent[ri] = rj;
+        size[rj] += size[ri];
+        merges.push(format!("Got {} at link {}: {} - {}", size[rj], rank, show(ix), show(jx)));
+      }
+    }
+    let sizes_after = sizes_after.unwrap_or_else(|| vec![boxes.len()]);
+
+    let out = common::run(env!("CARGO_BIN_EXE_day08"), &["pt2"], &input);
+    let got = out.lines().filter(|l| l.starts_with("Got")).collect::<Vec<_>>();
+    prop_assert_eq!(got, merges.iter().map(|m| m.as_str()).collect::<Vec<_>>());
+
+    let flag = format!("--links={}", links);
+    let out = common::run(env!("CARGO_BIN_EXE_day08"), &["pt1", &flag, "--top=1"], &input);
+    let expected = format!("sizes {:?}", sizes_after);
+    prop_assert_eq!(out.lines().next(), Some(expected.as_str()));
+  }
+}
+
+#[test]
+fn single_box() {
+  let path = env::temp_dir().join(format!("day08-single-{}.dot", std::process::id()));
+  let flag = format!("--mst={}", path.display());
+  let err = common::run_err(env!("CARGO_BIN_EXE_day08"), &["pt2", &flag], "1,2,3\n");
+  assert!(err.contains("Need at least two junction boxes"));
+
+  let dot = fs::read_to_string(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+  assert!(dot.contains("label=\"total length 0.000\""));
+}
+
+#[test]
+fn rejects_unknown_arguments() {
+  let err = |args: &[&str]| common::run_err(env!("CARGO_BIN_EXE_day08"), args, EXAMPLE);
+  assert!(err(&["pt1", "1000"]).contains("the number of links is given as --links=1000"));
+  assert!(err(&["pt1", "--link=5"]).contains("Unknown argument --link=5"));
+  assert!(err(&["pt3"]).contains("Unknown part pt3"));
+  assert_eq!(run(&["--links=10"], EXAMPLE), "40");
+}
+
+// Boxes spread over a cube of the given size, from a fixed seed
+fn scattered(count: usize, size: i64) -> Vec<(i64, i64, i64)> {
+  let mut state = 0x2545_f491_4f6c_dd1d_u64;
+  let mut next = move || {
+    state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (state >> 33) as i64 % size
+  };
+  (0..count).map(|_| (next(), next(), next())).collect()
+}
+
+fn to_input(boxes: &[(i64, i64, i64)]) -> String {
+  boxes.iter().map(|(x, y, z)| format!("{},{},{}\n", x, y, z)).collect()
+}
+
+#[test]
+fn many_boxes() {
+  // Far more boxes than the proptest, the last ones have nearly all their neighbours before them
+  let boxes = scattered(1000, 100_000);
+  let mut parent = (0..boxes.len()).collect::<Vec<_>>();
+  let mut merges = vec![];
+  for (rank, (ix, jx)) in sorted_pairs(&boxes).into_iter().enumerate() {
+    let (ri, rj) = (find(&mut parent, ix), find(&mut parent, jx));
+    if ri != rj {
+      parent[ri] = rj;
+      merges.push((rank, ix, jx));
+    }
+  }
+
+  let out = common::run(env!("CARGO_BIN_EXE_day08"), &["pt2"], &to_input(&boxes));
+  let got = out.lines().filter(|l| l.starts_with("Got")).collect::<Vec<_>>();
+  assert_eq!(got.len(), merges.len());
+  for (line, (rank, ix, jx)) in got.iter().zip(merges) {
+    let (a, b) = (boxes[ix], boxes[jx]);
+    let expected = format!(
+      " at link {}: {},{},{} - {},{},{}",
+      rank, a.0, a.1, a.2, b.0, b.1, b.2
+    );
+    assert!(
+      line.ends_with(&expected),
+      "{} does not end with {}",
+      line,
+      expected
+    );
+  }
+}
+
+#[test]
+fn scales_to_many_boxes() {
+  // Setting up the pairs takes a few seconds in a debug build, checking every pair would take
+  // minutes
+  let input = to_input(&scattered(50_000, 1_000_000));
+  let start = Instant::now();
+  let out = common::run(
+    env!("CARGO_BIN_EXE_day08"),
+    &["pt1", "--links=1", "--top=1"],
+    &input,
+  );
+  assert_eq!(out.lines().last(), Some("2"));
+  assert!(start.elapsed() < Duration::from_secs(60));
+}
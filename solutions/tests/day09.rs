@@ -0,0 +1,188 @@
+mod common;
+
+use proptest::prelude::*;
+
+fn run(args: &[&str], corners: &[(i64, i64)]) -> String {
+  let input = corners.iter().map(|(x, y)| format!("{},{}\n", x, y)).collect::<String>();
+  common::run(env!("CARGO_BIN_EXE_day09"), args, &input).trim().to_string()
+}
+
+const EXAMPLE: [(i64, i64); 8] = [
+  (7, 1),
+  (11, 1),
+  (11, 7),
+  (9, 7),
+  (9, 5),
+  (2, 5),
+  (2, 3),
+  (7, 3),
+];
+
+#[test]
+fn example() {
+  assert_eq!(run(&["pt1"], &EXAMPLE), "50");
+  assert_eq!(run(&["pt2"], &EXAMPLE), "24");
+  assert_eq!(run(&["pt2", "--engine=geometric"], &EXAMPLE), "24");
+}
+
+#[test]
+fn enclosed_area() {
+  // The whole loop, not just the largest rectangle inside it
+  let expected = "Enclosed area 46\n24";
+  assert_eq!(run(&["pt2", "--enclosed"], &EXAMPLE), expected);
+  assert_eq!(
+    run(&["pt2", "--enclosed", "--engine=geometric"], &EXAMPLE),
+    expected
+  );
+}
+
+#[test]
+fn narrow_notch() {
+  // The notch between x 2 and x 4 is a single column of tiles, it still splits the U in two
+  let u = [
+    (0, 0),
+    (6, 0),
+    (6, 4),
+    (4, 4),
+    (4, 2),
+    (2, 2),
+    (2, 4),
+    (0, 4),
+  ];
+  assert_eq!(run(&["pt2"], &u), "15");
+  assert_eq!(run(&["pt2", "--engine=geometric"], &u), "15");
+}
+
+#[test]
+fn adjacent_edges() {
+  // The edges at x 2 and x 3 leave no tile between them, the U is one solid block of tiles
+  let u = [
+    (0, 0),
+    (6, 0),
+    (6, 4),
+    (3, 4),
+    (3, 2),
+    (2, 2),
+    (2, 4),
+    (0, 4),
+  ];
+  assert_eq!(run(&["pt2"], &u), "35");
+  assert_eq!(run(&["pt2", "--engine=geometric"], &u), "35");
+}
+
+// An x-monotone rectilinear polygon: columns of the given widths, each spanning bottom..top and
+// overlapping the column before it so the outline stays a single loop.
+fn columns(spans: &[(i64, i64, i64)]) -> Vec<(i64, i64)> {
+  let mut xs = vec![0];
+  let mut ranges = Vec::<(i64, i64)>::new();
+  for &(width, bottom, height) in spans {
+    xs.push(xs.last().unwrap() + width);
+    let (mut b, mut t) = (bottom, bottom + height);
+    if let Some(&(pb, pt)) = ranges.last() {
+      b = b.min(pt - 1);
+      t = t.max(pb + 1);
+    }
+    ranges.push((b, t));
+  }
+
+  let n = ranges.len();
+  let mut corners = vec![(xs[0], ranges[0].0), (xs[0], ranges[0].1)];
+  for ix in 0..n {
+    corners.push((xs[ix + 1], ranges[ix].1));
+    if ix + 1 < n {
+      corners.push((xs[ix + 1], ranges[ix + 1].1));
+    }
+  }
+  corners.push((xs[n], ranges[n - 1].0));
+  for ix in (1..n).rev() {
+    corners.push((xs[ix], ranges[ix].0));
+    corners.push((xs[ix], ranges[ix - 1].0));
+  }
+
+  // Equal neighbouring heights leave corners that aren't corners, drop them
+  loop {
+    corners.dedup();
+    let len = corners.len();
+    let straight = (0..len).find(|&ix| {
+      let (a, b, c) = (
+        corners[(ix + len - 1) % len],
+        corners[ix],
+        corners[(ix + 1) % len],
+      );
+      (a.0 == b.0 && b.0 == c.0) || (a.1 == b.1 && b.1 == c.1)
+    });
+    match straight {
+      Some(ix) => corners.remove(ix),
+      None => break,
+    };
+  }
+  corners
+}
+
+// Checks every tile of every rectangle against the loop, one at a time
+fn brute_force(corners: &[(i64, i64)]) -> i64 {
+  let n = corners.len();
+  let edges = (0..n).map(|ix| (corners[ix], corners[(ix + 1) % n])).collect::<Vec<_>>();
+  let tile = |x: i64, y: i64| {
+    let on_edge = edges.iter().any(|&((sx, sy), (ex, ey))| {
+      sx.min(ex) <= x && x <= sx.max(ex) && sy.min(ey) <= y && y <= sy.max(ey)
+    });
+    let crossings = edges
+      .iter()
+      .filter(|&&((sx, sy), (ex, ey))| sx == ex && sx > x && sy.min(ey) <= y && y < sy.max(ey))
+      .count();
+    on_edge || crossings % 2 == 1
+  };
+
+  let mut best = 0;
+  for (ix, &(x1, y1)) in corners.iter().enumerate() {
+    for &(x2, y2) in &corners[ix + 1..] {
+      let fits = (x1.min(x2)..=x1.max(x2)).all(|x| (y1.min(y2)..=y1.max(y2)).all(|y| tile(x, y)));
+      if fits {
+        best = best.max(((x1 - x2).abs() + 1) * ((y1 - y2).abs() + 1));
+      }
+    }
+  }
+  best
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(64))]
+
+  // Gaps of a single tile between red tiles keep a position of their own on the tiny map, so
+  // notches one tile wide still cut into the loop
+  #[test]
+  fn flood_counts_tiles(spans in prop::collection::vec((1i64..4, 0i64..8, 1i64..8), 1..8)) {
+    let corners = columns(&spans);
+    prop_assert_eq!(run(&["pt2"], &corners), brute_force(&corners).to_string());
+  }
+
+  // Columns one wide put parallel edges next to each other, with no tile between them
+  #[test]
+  fn engines_agree(
+    spans in prop::collection::vec((1i64..5, 0i64..8, 1i64..8), 1..8),
+    transpose: bool,
+  ) {
+    let corners = columns(&spans)
+      .into_iter()
+      .map(|(x, y)| if transpose { (y, x) } else { (x, y) })
+      .collect::<Vec<_>>();
+    prop_assert_eq!(
+      run(&["pt2", "--enclosed"], &corners),
+      run(&["pt2", "--enclosed", "--engine=geometric"], &corners)
+    );
+  }
+}
+
+#[test]
+fn large_tiny_map() {
+  // A staircase of 1000 steps compresses to a map of about 2000x2000
+  let n = 1000;
+  let mut corners = vec![(0, 0), (2 * n, 0)];
+  for j in 1..=n {
+    corners.push((2 * (n - j + 1), 2 * j));
+    corners.push((2 * (n - j), 2 * j));
+  }
+  let best = (1..=n).map(|j| (2 * n - 2 * j + 3) * (2 * j + 1)).max().unwrap();
+  assert_eq!(run(&["pt2"], &corners), best.to_string());
+}
@@ -0,0 +1,38 @@
+use aoc_2025::grid::flood;
+
+const SIZE: usize = 2000;
+
+#[test]
+fn open_map() {
+  let mut map = vec![vec![false; SIZE]; SIZE];
+  assert_eq!(
+    flood(&mut map, SIZE / 2, SIZE / 2, false, true),
+    SIZE * SIZE
+  );
+  assert!(map.iter().flatten().all(|&c| c));
+}
+
+#[test]
+fn serpentine_map() {
+  // Walls on every odd row with a gap alternating between both ends, so the only way through is
+  // one path snaking over the whole map
+  let mut map = (0..SIZE)
+    .map(|y| match y % 4 {
+      1 => (0..SIZE).map(|x| x != SIZE - 1).collect(),
+      3 => (0..SIZE).map(|x| x != 0).collect(),
+      _ => vec![false; SIZE],
+    })
+    .collect::<Vec<Vec<bool>>>();
+  let open = map.iter().flatten().filter(|&&c| !c).count();
+  assert_eq!(flood(&mut map, 0, 0, false, true), open);
+  assert!(map.iter().flatten().all(|&c| c));
+}
+
+#[test]
+fn stops_at_borders() {
+  let mut map = vec![vec!['.'; 5]; 5];
+  map[2].fill('#');
+  assert_eq!(flood(&mut map, 4, 4, '.', 'o'), 10);
+  assert_eq!(map[0], ['.'; 5]);
+  assert_eq!(flood(&mut map, 0, 0, '.', '.'), 0);
+}